@@ -0,0 +1,285 @@
+use bevy::{
+    input::mouse::{AccumulatedMouseScroll, MouseScrollUnit},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use bevy_egui::{EguiContexts, egui::{self, Ui}, input::EguiWantsInput};
+use bevy_pancam::PanCam;
+use glam::DVec2;
+use rand::Rng;
+use rayon::prelude::*;
+use strum::{EnumIter, IntoEnumIterator};
+use std::fmt::{self, Debug, Display, Formatter};
+
+use crate::{
+    config::{ConfigState, MAX_BODIES},
+    palette::Palette,
+    physics::{bodies::PointBody, params::PhysicsParams, physics::ParticlePhysics},
+    render,
+};
+
+// defaults
+const RADIUS: f64 = 0.05; // unit squares
+const STRENGTH: f64 = 1.0;
+
+// limits
+const MIN_RADIUS: f64 = 0.005;
+const MAX_RADIUS: f64 = 0.5;
+const MAX_STRENGTH: f64 = 20.0;
+
+// particles spawned per second at strength 1
+const SPAWN_RATE: f64 = 500.0;
+// how much one notch of the scroll wheel scales the radius or strength
+const SCROLL_STEP: f64 = 1.1;
+
+// what left dragging in the world does
+#[derive(Clone, Copy, Default, EnumIter, PartialEq)]
+pub enum BrushMode {
+    // leave the mouse to the camera
+    #[default]
+    Pan,
+    Attract,
+    Repel,
+    Spawn,
+    Erase,
+    Recolor,
+}
+
+impl Debug for BrushMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match &self {
+            BrushMode::Pan => "Pan",
+            BrushMode::Attract => "Attract",
+            BrushMode::Repel => "Repel",
+            BrushMode::Spawn => "Spawn",
+            BrushMode::Erase => "Erase",
+            BrushMode::Recolor => "Recolor",
+        })
+    }
+}
+
+impl Display for BrushMode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+#[derive(Resource)]
+pub struct Brush {
+    pub mode: BrushMode,
+    pub radius: f64,
+    pub strength: f64,
+    pub color: usize, // what spawn and recolor paint with
+    cursor: Option<(Vec2, DVec2)>, // the cursor in the world and in the simulation
+    active: bool, // dragging in the world with a tool
+    spawn_debt: f64, // fractional particles carried over between frames
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            mode: BrushMode::default(),
+            radius: RADIUS,
+            strength: STRENGTH,
+            color: 0,
+            cursor: None,
+            active: false,
+            spawn_debt: 0.0,
+        }
+    }
+}
+
+impl Brush {
+
+    pub fn brush_ui(&mut self, ui: &mut Ui, palette: &Palette) {
+        ui.horizontal_wrapped(|ui| {
+            for mode in BrushMode::iter() {
+                ui.selectable_value(&mut self.mode, mode, format!("{mode}"));
+            }
+        });
+        ui.add(egui::Slider::new(&mut self.radius, MIN_RADIUS..=MAX_RADIUS)
+            .logarithmic(true)
+            .text("Radius"))
+            .on_hover_text("Ctrl + scroll");
+        ui.add(egui::Slider::new(&mut self.strength, 0.0..=MAX_STRENGTH)
+            .text("Strength"))
+            .on_hover_text("Ctrl + Shift + scroll");
+        ui.add_enabled_ui(matches!(self.mode, BrushMode::Spawn | BrushMode::Recolor), |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for color in 0..palette.len() {
+                    let (rect, response) = ui.allocate_exact_size(egui::vec2(14.0, 14.0), egui::Sense::click());
+                    ui.painter().rect_filled(rect, 2.0, palette.egui_color(color));
+                    if color == self.color {
+                        ui.painter().rect_stroke(rect, 2.0, (2.0, egui::Color32::WHITE), egui::StrokeKind::Outside);
+                    }
+                    if response.clicked() {
+                        self.color = color;
+                    }
+                }
+            });
+        });
+    }
+
+    // the offset from the brush center, if the position is under the brush
+    #[inline]
+    fn reach(&self, center: DVec2, pos: DVec2, params: &PhysicsParams) -> Option<DVec2> {
+        let offset = params.boundary.offset(center, pos);
+        (offset.length_squared() <= self.radius * self.radius).then_some(offset)
+    }
+
+}
+
+// tracks the cursor, hands the left button and the scroll wheel over from the
+// camera while a tool or modifier wants them
+pub fn update_brush(
+    mut brush: ResMut<Brush>,
+    mut camera: Query<(&Camera, &GlobalTransform, &mut PanCam)>,
+    config: Res<ConfigState>,
+    egui_input: Res<EguiWantsInput>,
+    key_state: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    params: Res<PhysicsParams>,
+    scroll: Res<AccumulatedMouseScroll>,
+    window: Single<&Window, With<PrimaryWindow>>,
+) {
+    let Ok((camera, camera_transform, mut pancam)) = camera.single_mut() else { return };
+    let tool = brush.mode != BrushMode::Pan;
+    let ctrl = key_state.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = key_state.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    // keep the left button for the tool, and the wheel for the brush size
+    let grab = match tool {
+        true => vec![MouseButton::Right, MouseButton::Middle],
+        false => vec![MouseButton::Left, MouseButton::Right, MouseButton::Middle],
+    };
+    if pancam.grab_buttons != grab {
+        pancam.grab_buttons = grab;
+    }
+    let scrolling = tool && ctrl;
+    if pancam.enabled == scrolling {
+        pancam.enabled = !scrolling;
+    }
+    if scrolling && scroll.delta.y != 0.0 {
+        let notches = match scroll.unit {
+            MouseScrollUnit::Line => scroll.delta.y as f64,
+            MouseScrollUnit::Pixel => scroll.delta.y as f64 / 40.0,
+        };
+        let scale = SCROLL_STEP.powf(notches);
+        match shift {
+            true => brush.strength = (brush.strength * scale).clamp(0.01, MAX_STRENGTH),
+            false => brush.radius = (brush.radius * scale).clamp(MIN_RADIUS, MAX_RADIUS),
+        }
+    }
+
+    brush.cursor = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+        .map(|world| (world, render::sim_position(world, config.half_side, config.view_center, params.boundary)));
+    let over_ui = egui_input.wants_any_pointer_input();
+    brush.active = tool && !over_ui && brush.cursor.is_some() && mouse.pressed(MouseButton::Left);
+}
+
+// spawn, erase and recolor work on the particles directly, once a frame
+pub fn paint_brush(
+    mut brush: ResMut<Brush>,
+    mut config: ResMut<ConfigState>,
+    mut physics: ResMut<ParticlePhysics>,
+    palette: Res<Palette>,
+    params: Res<PhysicsParams>,
+    time: Res<Time>,
+) {
+    if !brush.active { return }
+    let Some((_, center)) = brush.cursor else { return };
+    let particles = physics.particles_mut();
+    match brush.mode {
+        BrushMode::Spawn => {
+            let color = brush.color.min(palette.len().saturating_sub(1));
+            brush.spawn_debt += brush.strength * SPAWN_RATE * time.delta_secs_f64();
+            let room = (MAX_BODIES as usize).saturating_sub(particles.len());
+            let count = (brush.spawn_debt as usize).min(room);
+            brush.spawn_debt = brush.spawn_debt.fract();
+            // not the simulation's rng, so painting doesn't change what a seed does
+            let mut rng = rand::rng();
+            for _ in 0..count {
+                // uniform over the disk
+                let angle = rng.random_range(0.0..std::f64::consts::TAU);
+                let dist = brush.radius * rng.random::<f64>().sqrt();
+                let mut body = PointBody::new(color, center + dist * DVec2::from_angle(angle));
+                params.boundary.confine(&mut body.position, &mut body.velocity);
+                particles.push(body);
+            }
+        },
+        BrushMode::Erase => {
+            // backwards, so the bodies swapped in have already been checked
+            for ix in (0..particles.len()).rev() {
+                if brush.reach(center, particles.positions[ix], &params).is_some() {
+                    particles.swap_remove(ix);
+                }
+            }
+        },
+        BrushMode::Recolor => {
+            let color = brush.color.min(palette.len().saturating_sub(1));
+            particles.colors
+                .par_iter_mut()
+                .zip(particles.positions.par_iter())
+                .filter(|(_, pos)| brush.reach(center, **pos, &params).is_some())
+                .for_each(|(c, _)| *c = color);
+        },
+        _ => return,
+    }
+    // keep `match_body_count` from undoing the change
+    config.bodies_count = particles.len() as u32;
+}
+
+// attract and repel push on velocities once per tick, fading to nothing at
+// the edge of the brush
+pub fn push_brush(
+    mut physics: ResMut<ParticlePhysics>,
+    brush: Res<Brush>,
+    params: Res<PhysicsParams>,
+) {
+    if !brush.active { return }
+    let Some((_, center)) = brush.cursor else { return };
+    let sign = match brush.mode {
+        BrushMode::Attract => -1.0,
+        BrushMode::Repel => 1.0,
+        _ => return,
+    };
+    let impulse = sign * brush.strength * params.time_step;
+    let particles = physics.particles_mut();
+    particles.velocities
+        .par_iter_mut()
+        .zip(particles.positions.par_iter())
+        .for_each(|(velocity, pos)| {
+            if let Some(offset) = brush.reach(center, *pos, &params) {
+                let dist = offset.length();
+                if dist > 0.0 {
+                    *velocity += offset / dist * impulse * (1.0 - dist / brush.radius);
+                }
+            }
+        });
+}
+
+// the brush outline around the cursor
+pub fn draw_brush(
+    mut gui: EguiContexts,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    brush: Res<Brush>,
+    config: Res<ConfigState>,
+) {
+    if brush.mode == BrushMode::Pan { return }
+    let Some((world, _)) = brush.cursor else { return };
+    let Ok((camera, camera_transform)) = camera.single() else { return };
+    let Ok(ctx) = gui.ctx_mut() else { return };
+    let edge = world + Vec2::X * (brush.radius * 2.0) as f32 * config.half_side;
+    let (Ok(center), Ok(edge)) = (
+        camera.world_to_viewport(camera_transform, world.extend(0.0)),
+        camera.world_to_viewport(camera_transform, edge.extend(0.0)),
+    ) else { return };
+    let alpha = if brush.active { 200 } else { 100 };
+    ctx.layer_painter(egui::LayerId::background()).circle_stroke(
+        egui::pos2(center.x, center.y),
+        center.distance(edge),
+        (1.0, egui::Color32::from_white_alpha(alpha)),
+    );
+}
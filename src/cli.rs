@@ -0,0 +1,144 @@
+use std::{fmt::Display, path::PathBuf};
+
+use bevy::window::{MonitorSelection, VideoModeSelection, WindowMode};
+use clap::{Parser, ValueEnum};
+use strum::IntoEnumIterator;
+
+use crate::{
+    config::{ConfigState, MAX_BODIES},
+    physics::{forces::ForceMatrixType, params::{MAX_TIME_STEP, MIN_TIME_STEP, PhysicsParams}},
+    providers::positioners::PositionerType,
+};
+
+const DEFAULT_TICKS: u64 = 1000;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Particle life simulation")]
+pub struct Args {
+    /// Run the simulation without a window or renderer
+    #[arg(long)]
+    pub headless: bool,
+    /// Number of ticks to simulate in headless mode
+    #[arg(long, default_value_t = DEFAULT_TICKS, value_parser = clap::value_parser!(u64).range(1..))]
+    pub ticks: u64,
+    /// Seconds simulated per tick
+    #[arg(long, value_parser = parse_time_step)]
+    pub dt: Option<f64>,
+    /// Snapshot file written once a headless run finishes
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+    /// Number of particles
+    #[arg(long, value_parser = clap::value_parser!(u32).range(..=MAX_BODIES as i64))]
+    pub bodies: Option<u32>,
+    /// Number of particle colors
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..))]
+    pub colors: Option<u8>,
+    /// Force matrix generator, e.g. "random" or "snakes"
+    #[arg(long, value_parser = parse_named::<ForceMatrixType>)]
+    pub matrix: Option<ForceMatrixType>,
+    /// Initial particle positions, e.g. "uniform" or "s-ring"
+    #[arg(long, value_parser = parse_named::<PositionerType>)]
+    pub positions: Option<PositionerType>,
+    /// Seed for every random choice made while setting up the simulation
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Start from a preset file, or the name of one in the presets directory
+    #[arg(long, conflicts_with_all = ["headless", "snapshot", "bodies", "colors", "matrix", "positions", "dt"])]
+    pub preset: Option<String>,
+    /// Start from a snapshot file, bodies and all
+    #[arg(long, conflicts_with_all = ["headless", "bodies", "colors", "matrix", "positions", "seed", "dt"])]
+    pub snapshot: Option<PathBuf>,
+    /// How the window is shown
+    #[arg(long, value_enum, default_value_t = WindowArg::Borderless)]
+    pub window: WindowArg,
+    /// Window size, e.g. 1280x720; used in windowed mode
+    #[arg(long, value_parser = parse_size, default_value = "1280x720")]
+    pub size: (u32, u32),
+    /// Start with the simulation paused
+    #[arg(long)]
+    pub paused: bool,
+    /// Start with the ui hidden, Escape shows it
+    #[arg(long)]
+    pub hide_ui: bool,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum WindowArg {
+    /// Borderless fullscreen on the primary monitor
+    Borderless,
+    /// Exclusive fullscreen on the primary monitor
+    Fullscreen,
+    /// A window of `--size`
+    Windowed,
+}
+
+impl Args {
+    pub fn config(&self) -> ConfigState {
+        let mut config = ConfigState::default();
+        if let Some(bodies) = self.bodies {
+            config.bodies_count = bodies;
+        }
+        if let Some(colors) = self.colors {
+            config.colors_count = colors;
+        }
+        if let Some(matrix) = self.matrix {
+            config.force_matrix_option = matrix;
+        }
+        if let Some(positions) = self.positions {
+            config.position_option = positions;
+        }
+        if let Some(seed) = self.seed {
+            config.seed = seed;
+        }
+        config
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        match self.window {
+            WindowArg::Borderless => WindowMode::BorderlessFullscreen(MonitorSelection::Primary),
+            WindowArg::Fullscreen => WindowMode::Fullscreen(MonitorSelection::Primary, VideoModeSelection::Current),
+            WindowArg::Windowed => WindowMode::Windowed,
+        }
+    }
+
+    pub fn physics(&self) -> PhysicsParams {
+        let mut params = PhysicsParams::default();
+        if let Some(dt) = self.dt {
+            params.time_step = dt;
+        }
+        params
+    }
+}
+
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let parse = |s: &str| s.trim().parse::<u32>().ok().filter(|n| *n > 0);
+    value
+        .split_once(['x', 'X'])
+        .and_then(|(w, h)| Some((parse(w)?, parse(h)?)))
+        .ok_or_else(|| "expected WIDTHxHEIGHT, e.g. 1280x720".to_string())
+}
+
+fn parse_time_step(value: &str) -> Result<f64, String> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|dt| (MIN_TIME_STEP..=MAX_TIME_STEP).contains(dt))
+        .ok_or_else(|| format!("expected seconds between {MIN_TIME_STEP} and {MAX_TIME_STEP}"))
+}
+
+// match an option by its display name, ignoring case, spaces and dashes
+fn parse_named<T: Display + IntoEnumIterator>(value: &str) -> Result<T, String> {
+    let normalize = |s: &str| s
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .collect::<String>()
+        .to_lowercase();
+    let value = normalize(value);
+    T::iter()
+        .find(|option| normalize(&option.to_string()) == value)
+        .ok_or_else(|| format!(
+            "expected one of: {}",
+            T::iter().map(|option| option.to_string()).collect::<Vec<_>>().join(", "),
+        ))
+}
@@ -0,0 +1,183 @@
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display, Formatter},
+    fs,
+    io::{self, BufWriter, Write},
+};
+
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+// how many events the panel keeps, the file keeps everything
+const LOG_LEN: usize = 1000;
+const DEFAULT_PATH: &str = "cluster_events.log";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClusterEventKind {
+    Birth,
+    Death,
+    Split,
+    Merge,
+    Reset, // the bodies were respawned or removed, tracking started over
+}
+
+impl ClusterEventKind {
+    const ALL: [Self; 5] = [Self::Birth, Self::Death, Self::Split, Self::Merge, Self::Reset];
+
+    fn color(self) -> egui::Color32 {
+        match self {
+            Self::Birth => egui::Color32::from_rgb(120, 220, 120),
+            Self::Death => egui::Color32::from_rgb(160, 160, 160),
+            Self::Split => egui::Color32::from_rgb(240, 200, 90),
+            Self::Merge => egui::Color32::from_rgb(120, 170, 255),
+            Self::Reset => egui::Color32::from_rgb(230, 110, 110),
+        }
+    }
+}
+
+impl Display for ClusterEventKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            Self::Birth => "birth",
+            Self::Death => "death",
+            Self::Split => "split",
+            Self::Merge => "merge",
+            Self::Reset => "reset",
+        })
+    }
+}
+
+// one change between two labellings, the clusters on each side as (id, size)
+pub struct ClusterEvent {
+    pub time: f64,
+    pub kind: ClusterEventKind,
+    pub before: Vec<(u64, usize)>,
+    pub after: Vec<(u64, usize)>,
+}
+
+impl ClusterEvent {
+    pub fn reset(time: f64) -> Self {
+        Self { time, kind: ClusterEventKind::Reset, before: Vec::new(), after: Vec::new() }
+    }
+}
+
+// e.g. "12.50s split #4 (120) -> #4 (70) #9 (48)"
+impl Display for ClusterEvent {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.kind == ClusterEventKind::Reset {
+            return write!(f, "{:.2}s {}", self.time, self.kind);
+        }
+        let side = |clusters: &[(u64, usize)]| clusters
+            .iter()
+            .map(|(id, size)| format!("#{id} ({size})"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        write!(f, "{:.2}s {} {} -> {}", self.time, self.kind, side(&self.before), side(&self.after))
+    }
+}
+
+#[derive(Resource)]
+pub struct ClusterLog {
+    events: VecDeque<ClusterEvent>,
+    counts: [usize; 5], // every event so far, by kind
+    shown: [bool; 5], // which kinds the panel lists
+    path: String,
+    write_file: bool,
+    file: Option<BufWriter<fs::File>>,
+    status: Option<String>,
+}
+
+impl Default for ClusterLog {
+    fn default() -> Self {
+        Self {
+            events: VecDeque::with_capacity(LOG_LEN),
+            counts: [0; 5],
+            shown: [true; 5],
+            path: DEFAULT_PATH.to_string(),
+            write_file: false,
+            file: None,
+            status: None,
+        }
+    }
+}
+
+impl ClusterLog {
+
+    pub fn record(&mut self, events: Vec<ClusterEvent>) {
+        if events.is_empty() { return }
+        if self.write_file
+            && let Err(err) = self.write(&events)
+        {
+            self.status = Some(format!("write failed: {err}"));
+            self.write_file = false;
+            self.file = None;
+        }
+        for event in events {
+            self.counts[event.kind as usize] += 1;
+            if self.events.len() == LOG_LEN {
+                self.events.pop_front();
+            }
+            self.events.push_back(event);
+        }
+    }
+
+    // append to the file, opening it on the first write
+    fn write(&mut self, events: &[ClusterEvent]) -> io::Result<()> {
+        if self.file.is_none() {
+            let file = fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+            self.file = Some(BufWriter::new(file));
+            self.status = Some(format!("writing to {}", self.path));
+        }
+        let Some(file) = &mut self.file else { return Ok(()) };
+        for event in events {
+            writeln!(file, "{event}")?;
+        }
+        file.flush()
+    }
+
+}
+
+pub fn cluster_log_ui(
+    mut gui: EguiContexts,
+    mut log: ResMut<ClusterLog>,
+) {
+    let Ok(ctx) = gui.ctx_mut() else { return };
+    egui::Window::new("Cluster Events")
+        .default_open(false)
+        .default_width(320.0)
+        .show(ctx, |ui| {
+            // counts double as the filters
+            ui.horizontal(|ui| {
+                for kind in ClusterEventKind::ALL {
+                    let ix = kind as usize;
+                    let text = egui::RichText::new(format!("{kind}s {}", log.counts[ix])).color(kind.color());
+                    ui.toggle_value(&mut log.shown[ix], text);
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.checkbox(&mut log.write_file, "Write to").changed() && !log.write_file {
+                    log.file = None;
+                    log.status = None;
+                }
+                // the path is locked while writing so partial edits don't each get a file
+                let write_file = log.write_file;
+                ui.add_enabled(!write_file, egui::TextEdit::singleline(&mut log.path));
+            });
+            if let Some(status) = &log.status {
+                ui.label(status);
+            }
+            if ui.button(" Clear ").clicked() {
+                log.events.clear();
+                log.counts = [0; 5];
+            }
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .max_height(240.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for event in log.events.iter().filter(|event| log.shown[event.kind as usize]) {
+                        ui.colored_label(event.kind.color(), event.to_string());
+                    }
+                });
+        });
+}
@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+use glam::DVec2;
+
+use crate::{
+    cluster_log::{ClusterEvent, ClusterEventKind, ClusterLog},
+    config::ConfigState,
+    palette::Palette,
+    physics::{boundary::BoundaryMode, params::PhysicsParams, physics::ParticlePhysics},
+    render,
+};
+
+// defaults
+const LINK_SCALE: f64 = 0.5; // of the interaction radius
+const MIN_SIZE: usize = 20;
+// seconds between detections, finding components is a walk over every pair
+const INTERVAL: f64 = 0.25;
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortKey {
+    Id,
+    Size,
+    Speed,
+    Age,
+}
+
+// a connected group of particles, kept under one id while most of its
+// members stay together
+pub struct Cluster {
+    pub id: u64,
+    pub size: usize,
+    pub composition: Vec<usize>, // members per color
+    pub centroid: DVec2,
+    pub velocity: DVec2, // the mean member velocity
+    pub radius: f64, // the furthest member from the centroid
+    pub born: f64, // seconds since startup
+    pub age: f64,
+    members: Vec<usize>,
+}
+
+impl Cluster {
+    fn new(members: Vec<usize>, physics: &ParticlePhysics, colors: usize, boundary: BoundaryMode) -> Self {
+        let particles = physics.particles();
+        let mut composition = vec![0; colors];
+        // offsets from one member, so a cluster across the seam stays whole
+        let origin = particles.positions[members[0]];
+        let mut offsets = DVec2::ZERO;
+        let mut velocity = DVec2::ZERO;
+        for ix in &members {
+            if let Some(count) = composition.get_mut(particles.colors[*ix]) {
+                *count += 1;
+            }
+            offsets += boundary.offset(origin, particles.positions[*ix]);
+            velocity += particles.velocities[*ix];
+        }
+        let size = members.len();
+        let mean_offset = offsets / size as f64;
+        let radius = members
+            .iter()
+            .map(|ix| boundary.offset(origin, particles.positions[*ix]).distance(mean_offset))
+            .fold(0.0, f64::max);
+        let centroid = origin + mean_offset;
+        Self {
+            id: 0,
+            size,
+            composition,
+            centroid: match boundary.wraps() {
+                true => centroid.rem_euclid(DVec2::ONE),
+                false => centroid,
+            },
+            velocity: velocity / size as f64,
+            radius,
+            born: 0.0,
+            age: 0.0,
+            members,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct ClusterTracker {
+    pub link_scale: f64,
+    pub min_size: usize,
+    pub interval: f64, // zero labels every frame
+    pub overlay: bool,
+    clusters: Vec<Cluster>,
+    next_id: u64,
+    generation: Option<u64>, // the bodies' generation the clusters were labelled in
+    last_run: f64,
+    sort: (SortKey, bool), // and whether it's descending
+}
+
+impl Default for ClusterTracker {
+    fn default() -> Self {
+        Self {
+            link_scale: LINK_SCALE,
+            min_size: MIN_SIZE,
+            interval: INTERVAL,
+            overlay: true,
+            clusters: Vec::new(),
+            next_id: 0,
+            generation: None,
+            last_run: f64::NEG_INFINITY,
+            sort: (SortKey::Size, true),
+        }
+    }
+}
+
+impl ClusterTracker {
+
+    // start the ids over once the bodies have changed indices, matching
+    // clusters by their members' indices would pair up unrelated clumps
+    fn restart(&mut self, mut clusters: Vec<Cluster>, now: f64) {
+        for (id, cluster) in clusters.iter_mut().enumerate() {
+            cluster.id = id as u64;
+            cluster.born = now;
+            cluster.age = 0.0;
+        }
+        self.next_id = clusters.len() as u64;
+        self.clusters = clusters;
+    }
+
+    // carry ids over to the new clusters and report what happened between
+    // the two labellings. an old cluster hands a significant share of its
+    // members to each of its successors, the biggest share keeps its id;
+    // more than one successor is a split, more than one predecessor a merge,
+    // and none at all a death or a birth
+    fn track(&mut self, mut clusters: Vec<Cluster>, body_count: usize, now: f64) -> Vec<ClusterEvent> {
+        let mut owners = vec![None; body_count];
+        for (cx, cluster) in self.clusters.iter().enumerate() {
+            for ix in &cluster.members {
+                if let Some(owner) = owners.get_mut(*ix) {
+                    *owner = Some(cx);
+                }
+            }
+        }
+        let significant = (self.min_size / 2).max(1);
+        let mut overlaps = Vec::new();
+        for (nx, cluster) in clusters.iter().enumerate() {
+            let mut shared = HashMap::new();
+            for cx in cluster.members.iter().filter_map(|ix| owners[*ix]) {
+                *shared.entry(cx).or_insert(0usize) += 1;
+            }
+            overlaps.extend(shared
+                .into_iter()
+                .filter(|(_, count)| *count >= significant)
+                .map(|(cx, count)| (count, nx, cx)));
+        }
+        overlaps.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut successors = vec![Vec::new(); self.clusters.len()];
+        let mut predecessors = vec![Vec::new(); clusters.len()];
+        let mut matched = vec![None; clusters.len()];
+        let mut taken = vec![false; self.clusters.len()];
+        for (_, nx, cx) in overlaps {
+            successors[cx].push(nx);
+            predecessors[nx].push(cx);
+            if matched[nx].is_none() && !taken[cx] {
+                matched[nx] = Some(cx);
+                taken[cx] = true;
+            }
+        }
+        for (cluster, old) in clusters.iter_mut().zip(matched) {
+            match old {
+                Some(cx) => {
+                    cluster.id = self.clusters[cx].id;
+                    cluster.born = self.clusters[cx].born;
+                },
+                None => {
+                    cluster.id = self.next_id;
+                    cluster.born = now;
+                    self.next_id += 1;
+                },
+            }
+            cluster.age = now - cluster.born;
+        }
+
+        let mut events = Vec::new();
+        let tag = |cluster: &Cluster| (cluster.id, cluster.size);
+        for (old, successors) in self.clusters.iter().zip(&successors) {
+            let kind = match successors.len() {
+                0 => ClusterEventKind::Death,
+                1 => continue,
+                _ => ClusterEventKind::Split,
+            };
+            let after = successors.iter().map(|nx| tag(&clusters[*nx])).collect();
+            events.push(ClusterEvent { time: now, kind, before: vec![tag(old)], after });
+        }
+        for (new, predecessors) in clusters.iter().zip(&predecessors) {
+            let kind = match predecessors.len() {
+                0 => ClusterEventKind::Birth,
+                1 => continue,
+                _ => ClusterEventKind::Merge,
+            };
+            let before = predecessors.iter().map(|cx| tag(&self.clusters[*cx])).collect();
+            events.push(ClusterEvent { time: now, kind, before, after: vec![tag(new)] });
+        }
+        self.clusters = clusters;
+        events
+    }
+
+    fn sorted(&self) -> Vec<&Cluster> {
+        let mut clusters = self.clusters.iter().collect::<Vec<_>>();
+        let (key, descending) = self.sort;
+        clusters.sort_by(|a, b| {
+            let order = match key {
+                SortKey::Id => a.id.cmp(&b.id),
+                SortKey::Size => a.size.cmp(&b.size),
+                SortKey::Speed => a.velocity.length().total_cmp(&b.velocity.length()),
+                SortKey::Age => a.age.total_cmp(&b.age),
+            };
+            match descending {
+                true => order.reverse(),
+                false => order,
+            }
+        });
+        clusters
+    }
+
+    fn sort_header(&mut self, ui: &mut egui::Ui, key: SortKey, label: &str) {
+        let (current, descending) = self.sort;
+        let text = match (current == key, descending) {
+            (true, true) => format!("{label} ⏷"),
+            (true, false) => format!("{label} ⏶"),
+            (false, _) => label.to_string(),
+        };
+        if ui.selectable_label(current == key, text).clicked() {
+            self.sort = (key, current != key || !descending);
+        }
+    }
+
+}
+
+// labels the clusters every `interval` seconds while the simulation runs
+pub fn detect_clusters(
+    mut log: ResMut<ClusterLog>,
+    mut physics: ResMut<ParticlePhysics>,
+    mut tracker: ResMut<ClusterTracker>,
+    palette: Res<Palette>,
+    params: Res<PhysicsParams>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs_f64();
+    if now - tracker.last_run < tracker.interval { return }
+    let roots = physics.connected_components(tracker.link_scale * params.max_dist);
+    tracker.last_run = now;
+
+    let mut groups = HashMap::<usize, Vec<usize>>::new();
+    for (ix, root) in roots.iter().enumerate() {
+        groups.entry(*root).or_default().push(ix);
+    }
+    let clusters = groups
+        .into_values()
+        .filter(|members| members.len() >= tracker.min_size)
+        .map(|members| Cluster::new(members, &physics, palette.len(), params.boundary))
+        .collect();
+    // events across a respawn or removal would be artifacts of the indices
+    // changing hands, so the log gets one marker instead
+    let generation = physics.particles().generation();
+    if tracker.generation != Some(generation) {
+        if tracker.generation.is_some() {
+            log.record(vec![ClusterEvent::reset(now)]);
+        }
+        tracker.generation = Some(generation);
+        tracker.restart(clusters, now);
+        return;
+    }
+    let events = tracker.track(clusters, roots.len(), now);
+    log.record(events);
+}
+
+pub fn clusters_ui(
+    mut gui: EguiContexts,
+    mut tracker: ResMut<ClusterTracker>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    config: Res<ConfigState>,
+    palette: Res<Palette>,
+    params: Res<PhysicsParams>,
+) {
+    let Ok(ctx) = gui.ctx_mut() else { return };
+
+    // outline each cluster where it's drawn
+    if tracker.overlay && let Ok((camera, camera_transform)) = camera.single() {
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        let side = 2.0 * config.half_side;
+        for cluster in &tracker.clusters {
+            let world = render::world_position(cluster.centroid, config.half_side, config.view_center, params.boundary);
+            let edge = world + Vec2::X * cluster.radius as f32 * side;
+            let (Ok(center), Ok(edge)) = (
+                camera.world_to_viewport(camera_transform, world.extend(0.0)),
+                camera.world_to_viewport(camera_transform, edge.extend(0.0)),
+            ) else { continue };
+            let center = egui::pos2(center.x, center.y);
+            let radius = center.distance(egui::pos2(edge.x, edge.y));
+            painter.circle_stroke(center, radius, (1.0, egui::Color32::from_white_alpha(120)));
+            painter.text(
+                center + egui::vec2(0.0, -radius - 2.0),
+                egui::Align2::CENTER_BOTTOM,
+                format!("#{}", cluster.id),
+                egui::FontId::proportional(11.0),
+                egui::Color32::from_white_alpha(180),
+            );
+        }
+    }
+
+    egui::Window::new("Clusters")
+        .default_open(false)
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .show(ctx, |ui| {
+            ui.add(egui::Slider::new(&mut tracker.link_scale, 0.05..=1.0)
+                .text("Link distance"))
+                .on_hover_text("How close particles have to be to join, relative to the interaction radius");
+            ui.add(egui::Slider::new(&mut tracker.min_size, 2..=1000)
+                .logarithmic(true)
+                .text("Min size"));
+            ui.add(egui::Slider::new(&mut tracker.interval, 0.0..=2.0)
+                .suffix("s")
+                .text("Every"))
+                .on_hover_text("Seconds between labellings, zero is every frame");
+            ui.checkbox(&mut tracker.overlay, "Outlines");
+            ui.label(format!("{} clusters", tracker.clusters.len()));
+
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                egui::Grid::new("clusters").striped(true).num_columns(5).show(ui, |ui| {
+                    tracker.sort_header(ui, SortKey::Id, "Id");
+                    tracker.sort_header(ui, SortKey::Size, "Size");
+                    ui.label("Colors");
+                    tracker.sort_header(ui, SortKey::Speed, "Speed");
+                    tracker.sort_header(ui, SortKey::Age, "Age");
+                    ui.end_row();
+                    for cluster in tracker.sorted() {
+                        ui.label(format!("#{}", cluster.id));
+                        ui.label(cluster.size.to_string());
+                        composition_bar(ui, &cluster.composition, cluster.size, &palette);
+                        ui.label(format!("{:.4}", cluster.velocity.length()));
+                        ui.label(format!("{:.1}s", cluster.age));
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+}
+
+// a bar split by how many members each color has
+fn composition_bar(ui: &mut egui::Ui, composition: &[usize], size: usize, palette: &Palette) {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(80.0, 10.0), egui::Sense::hover());
+    let mut x = rect.min.x;
+    for (color, count) in composition.iter().enumerate().filter(|(_, count)| **count > 0) {
+        let width = rect.width() * *count as f32 / size as f32;
+        let part = egui::Rect::from_min_size(egui::pos2(x, rect.min.y), egui::vec2(width, rect.height()));
+        ui.painter().rect_filled(part, 0.0, palette.egui_color(color));
+        x += width;
+    }
+    response.on_hover_text(composition
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count > 0)
+        .map(|(color, count)| format!("color {color}: {count}"))
+        .collect::<Vec<_>>()
+        .join("\n"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODIES: usize = 200;
+
+    // members `start..end`
+    fn cluster((start, end): (usize, usize)) -> Cluster {
+        let members = start..end;
+        Cluster {
+            id: 0,
+            size: members.len(),
+            composition: Vec::new(),
+            centroid: DVec2::ZERO,
+            velocity: DVec2::ZERO,
+            radius: 0.0,
+            born: 0.0,
+            age: 0.0,
+            members: members.collect(),
+        }
+    }
+
+    fn track(tracker: &mut ClusterTracker, clusters: &[(usize, usize)], now: f64) -> Vec<(ClusterEventKind, Vec<u64>, Vec<u64>)> {
+        let clusters = clusters.iter().copied().map(cluster).collect();
+        tracker.track(clusters, BODIES, now)
+            .into_iter()
+            .map(|event| {
+                let ids = |side: &[(u64, usize)]| side.iter().map(|(id, _)| *id).collect();
+                (event.kind, ids(&event.before), ids(&event.after))
+            })
+            .collect()
+    }
+
+    fn ids(tracker: &ClusterTracker) -> Vec<u64> {
+        tracker.clusters.iter().map(|cluster| cluster.id).collect()
+    }
+
+    #[test]
+    fn new_clusters_are_born_and_kept() {
+        let mut tracker = ClusterTracker::default();
+        let events = track(&mut tracker, &[(0, 30), (30, 60)], 1.0);
+        assert_eq!(events, [
+            (ClusterEventKind::Birth, vec![], vec![0]),
+            (ClusterEventKind::Birth, vec![], vec![1]),
+        ]);
+        // members drifting in and out don't change who's who
+        assert!(track(&mut tracker, &[(0, 28), (32, 65)], 3.0).is_empty());
+        assert_eq!(ids(&tracker), [0, 1]);
+        assert_eq!(tracker.clusters[0].age, 2.0);
+    }
+
+    #[test]
+    fn a_vanished_cluster_dies() {
+        let mut tracker = ClusterTracker::default();
+        track(&mut tracker, &[(0, 30), (30, 60)], 1.0);
+        let events = track(&mut tracker, &[(0, 30)], 2.0);
+        assert_eq!(events, [(ClusterEventKind::Death, vec![1], vec![])]);
+    }
+
+    #[test]
+    fn the_biggest_share_of_a_split_keeps_the_id() {
+        let mut tracker = ClusterTracker::default();
+        track(&mut tracker, &[(0, 60)], 1.0);
+        let events = track(&mut tracker, &[(0, 25), (25, 60)], 2.0);
+        assert_eq!(ids(&tracker), [1, 0]);
+        assert_eq!(events, [(ClusterEventKind::Split, vec![0], vec![0, 1])]);
+        assert_eq!(tracker.clusters[1].born, 1.0);
+        assert_eq!(tracker.clusters[0].born, 2.0);
+    }
+
+    #[test]
+    fn the_biggest_share_of_a_merge_keeps_the_id() {
+        let mut tracker = ClusterTracker::default();
+        track(&mut tracker, &[(0, 25), (25, 60)], 1.0);
+        let events = track(&mut tracker, &[(0, 60)], 2.0);
+        assert_eq!(ids(&tracker), [1]);
+        assert_eq!(events, [(ClusterEventKind::Merge, vec![1, 0], vec![1])]);
+    }
+
+    #[test]
+    fn overlaps_under_half_the_min_size_dont_count() {
+        let significant = ClusterTracker::default().min_size / 2;
+        let mut tracker = ClusterTracker::default();
+        track(&mut tracker, &[(0, 60)], 1.0);
+        // too little of the old cluster to carry it on
+        let events = track(&mut tracker, &[(0, significant - 1), (100, 130)], 2.0);
+        assert_eq!(events, [
+            (ClusterEventKind::Death, vec![0], vec![]),
+            (ClusterEventKind::Birth, vec![], vec![1]),
+            (ClusterEventKind::Birth, vec![], vec![2]),
+        ]);
+        // but just enough is a continuation
+        let mut tracker = ClusterTracker::default();
+        track(&mut tracker, &[(0, 60)], 1.0);
+        assert!(track(&mut tracker, &[(0, significant)], 2.0).is_empty());
+        assert_eq!(ids(&tracker), [0]);
+    }
+}
@@ -1,23 +1,33 @@
 use bevy::prelude::*;
+use glam::DVec2;
 use crate::{
-    physics::forces::{ForceMatrixType, RandomForceMatrix},
-    providers::positioners::PositionerType
+    physics::forces::{ForceMatrixType, MatrixLayer, RandomForceMatrix},
+    providers::positioners::PositionerType,
+    render::TilingMode,
 };
 
 // defaults
-const BODIES: u16 = 20_000;
+const BODIES: u32 = 20_000;
 const COLORS: u8 = 7;
 
+// limits
+pub const MAX_BODIES: u32 = 2_000_000;
+
 #[derive(Clone, Debug, Resource)]
 pub struct ConfigState {
-    pub bodies_count: u16,
+    pub bodies_count: u32,
     pub colors_count: u8,
     pub force_matrix_option: ForceMatrixType,
+    pub matrix_layer: MatrixLayer,
     pub half_side: f32,
-    pub body_mesh: Option<Handle<Mesh>>,
     pub panel_width: f32,
     pub position_option: PositionerType,
     pub reset_bodies: bool,
+    pub restart: bool, // reseed and rebuild forces and bodies, all in `match_body_count`
+    pub seed: u64,
+    pub tiling: TilingMode,
+    pub view_center: DVec2, // the spot drawn at the middle of the square
+    pub focus_ix: usize, // the particle the view can be centered on
 }
 
 impl Default for ConfigState {
@@ -26,11 +36,16 @@ impl Default for ConfigState {
             bodies_count: BODIES,
             colors_count: COLORS,
             force_matrix_option: ForceMatrixType::Random(RandomForceMatrix),
+            matrix_layer: MatrixLayer::default(),
             half_side: 0.0,
-            body_mesh: None,
             panel_width: 200.0,
             position_option: PositionerType::Uniform,
             reset_bodies: true,
+            restart: false,
+            seed: rand::random(),
+            tiling: TilingMode::default(),
+            view_center: DVec2::splat(0.5),
+            focus_ix: 0,
         }
     }
 }
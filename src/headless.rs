@@ -0,0 +1,94 @@
+use std::{path::PathBuf, time::{Duration, Instant}};
+
+use bevy::{app::ScheduleRunnerPlugin, prelude::*, time::TimeUpdateStrategy};
+
+use crate::{
+    cli::Args,
+    config::ConfigState,
+    physics::{
+        forces::ForceMatrix,
+        params::PhysicsParams,
+        physics::ParticlePhysics,
+        species::SpeciesTable,
+        plugin::{ParticlePhysicsPlugin, PhysicsSystems},
+    },
+    random_body,
+    respawn_absorbed,
+    rng::SimRng,
+    snapshot::SaveState,
+};
+
+#[derive(Resource)]
+struct HeadlessRun {
+    output: Option<PathBuf>,
+    started: Instant,
+    tick: u64,
+    ticks: u64,
+}
+
+// steps the simulation as fast as possible, without a window or renderer
+pub fn run(args: &Args, config: ConfigState, params: PhysicsParams) -> AppExit {
+    let colors_count = config.colors_count as usize;
+    let mut rng = SimRng::new(config.seed);
+    // advance time by exactly one fixed step per update
+    let timestep = Duration::from_secs_f64(params.time_step);
+    App::new()
+        .add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::ZERO)),
+            ParticlePhysicsPlugin,
+        ))
+        .insert_resource(Time::<Fixed>::from_duration(timestep))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(timestep))
+        .insert_resource(ForceMatrix::new(colors_count, config.force_matrix_option, &mut rng))
+        .insert_resource(HeadlessRun {
+            output: args.output.clone(),
+            started: Instant::now(),
+            tick: 0,
+            ticks: args.ticks,
+        })
+        .insert_resource(config)
+        .insert_resource(params)
+        .insert_resource(rng)
+        .add_systems(Startup, spawn_bodies)
+        .add_systems(FixedUpdate, (
+            respawn_absorbed.in_set(PhysicsSystems::Writeback),
+            finish_run.after(PhysicsSystems::Writeback),
+        ))
+        .run()
+}
+
+fn spawn_bodies(mut physics: ResMut<ParticlePhysics>, mut rng: ResMut<SimRng>, config: Res<ConfigState>) {
+    *physics.particles_mut() = (0..config.bodies_count)
+        .map(|_| random_body(&config, &mut *rng))
+        .collect();
+}
+
+fn finish_run(
+    mut exit: MessageWriter<AppExit>,
+    mut run: ResMut<HeadlessRun>,
+    config: Res<ConfigState>,
+    force_matrix: Res<ForceMatrix>,
+    params: Res<PhysicsParams>,
+    physics: Res<ParticlePhysics>,
+    species: Res<SpeciesTable>,
+) {
+    run.tick += 1;
+    if run.tick < run.ticks { return }
+
+    let elapsed = run.started.elapsed().as_secs_f64();
+    println!(
+        "simulated {} bodies for {} ticks in {elapsed:.2}s ({:.1} ticks/s)",
+        physics.particles().len(), run.tick, run.tick as f64 / elapsed,
+    );
+    if let Some(path) = &run.output {
+        match SaveState::new(physics.particles(), &config, &force_matrix, &params, &species).write(path) {
+            Ok(()) => println!("wrote snapshot to {}", path.display()),
+            Err(err) => {
+                eprintln!("failed to write snapshot to {}: {err}", path.display());
+                exit.write(AppExit::error());
+                return;
+            },
+        }
+    }
+    exit.write(AppExit::Success);
+}
@@ -0,0 +1,239 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_egui::{egui::{self, Ui}, input::EguiWantsInput};
+
+use crate::{config::ConfigState, physics::forces::ForceMatrix};
+
+// how many matrices are kept before the oldest is dropped
+const HISTORY_LEN: usize = 64;
+
+struct HistoryEntry {
+    id: u64,
+    matrix: ForceMatrix,
+}
+
+// every distinct force matrix the app has had, most recent last, with the
+// cursor on the one in use; undoing and then editing drops the redo states
+#[derive(Default, Resource)]
+pub struct MatrixHistory {
+    entries: VecDeque<HistoryEntry>,
+    cursor: usize,
+    next_id: u64,
+}
+
+impl MatrixHistory {
+
+    fn current(&self) -> Option<&ForceMatrix> {
+        self.entries.get(self.cursor).map(|entry| &entry.matrix)
+    }
+
+    fn push(&mut self, matrix: ForceMatrix) {
+        self.entries.truncate(self.cursor + 1);
+        self.entries.push_back(HistoryEntry { id: self.next_id, matrix });
+        self.next_id += 1;
+        if self.entries.len() > HISTORY_LEN {
+            self.entries.pop_front();
+        }
+        self.cursor = self.entries.len() - 1;
+    }
+
+    // keep a matrix that was edited since it was last recorded
+    fn record(&mut self, matrix: &ForceMatrix) {
+        if self.current() != Some(matrix) {
+            self.push(matrix.clone());
+        }
+    }
+
+    // move to entry `ix` and put its matrix and color count in use
+    fn restore(&mut self, ix: usize, force_matrix: &mut ForceMatrix, config: &mut ConfigState) {
+        let Some(entry) = self.entries.get(ix) else { return };
+        self.cursor = ix;
+        *force_matrix = entry.matrix.clone();
+        config.colors_count = force_matrix.color_count() as u8;
+    }
+
+    pub fn undo(&mut self, force_matrix: &mut ForceMatrix, config: &mut ConfigState) {
+        self.record(force_matrix);
+        if self.cursor > 0 {
+            self.restore(self.cursor - 1, force_matrix, config);
+        }
+    }
+
+    pub fn redo(&mut self, force_matrix: &mut ForceMatrix, config: &mut ConfigState) {
+        self.record(force_matrix);
+        self.restore(self.cursor + 1, force_matrix, config);
+    }
+
+    pub fn history_ui(&mut self, ui: &mut Ui, force_matrix: &mut ForceMatrix, config: &mut ConfigState) {
+        ui.horizontal(|ui| {
+            if ui.add_enabled(self.cursor > 0, egui::Button::new(" Undo ")).on_hover_text("Ctrl+Z").clicked() {
+                self.undo(force_matrix, config);
+            }
+            let can_redo = self.cursor + 1 < self.entries.len();
+            if ui.add_enabled(can_redo, egui::Button::new(" Redo ")).on_hover_text("Ctrl+Shift+Z").clicked() {
+                self.redo(force_matrix, config);
+            }
+        });
+        let mut clicked = None;
+        egui::ScrollArea::vertical()
+            .id_salt("matrix_history")
+            .max_height(120.0)
+            .show(ui, |ui| {
+                // newest first
+                for (ix, entry) in self.entries.iter().enumerate().rev() {
+                    let label = format!(
+                        "#{} {} colors, {:?}",
+                        entry.id, entry.matrix.color_count(), entry.matrix.matrix_type(),
+                    );
+                    if ui.selectable_label(ix == self.cursor, label).clicked() {
+                        clicked = Some(ix);
+                    }
+                }
+            });
+        if let Some(ix) = clicked {
+            self.record(force_matrix);
+            self.restore(ix, force_matrix, config);
+        }
+    }
+
+}
+
+// records the matrix once an edit is finished, so a drag is one entry
+pub fn record_matrix_history(
+    mut history: ResMut<MatrixHistory>,
+    force_matrix: Res<ForceMatrix>,
+    mouse: Res<ButtonInput<MouseButton>>,
+) {
+    if mouse.pressed(MouseButton::Left) { return }
+    history.record(&force_matrix);
+}
+
+pub fn undo_redo(
+    mut config: ResMut<ConfigState>,
+    mut force_matrix: ResMut<ForceMatrix>,
+    mut history: ResMut<MatrixHistory>,
+    egui_input: Res<EguiWantsInput>,
+    key_state: Res<ButtonInput<KeyCode>>,
+) {
+    // leave text fields their own undo
+    if egui_input.wants_any_keyboard_input() { return }
+    let ctrl = key_state.any_pressed([
+        KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::SuperLeft, KeyCode::SuperRight,
+    ]);
+    if !ctrl { return }
+    match key_state.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        true => history.redo(&mut force_matrix, &mut config),
+        false => history.undo(&mut force_matrix, &mut config),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use rand::{SeedableRng, rngs::StdRng};
+    use crate::physics::forces::{ForceMatrixType, RandomForceMatrix};
+
+    fn matrix(colors: usize, seed: u64) -> ForceMatrix {
+        ForceMatrix::new(colors, ForceMatrixType::Random(RandomForceMatrix), &mut StdRng::seed_from_u64(seed))
+    }
+
+    fn history(matrices: &[ForceMatrix]) -> MatrixHistory {
+        let mut history = MatrixHistory::default();
+        for matrix in matrices {
+            history.record(matrix);
+        }
+        history
+    }
+
+    #[test]
+    fn undo_and_redo_walk_the_entries() {
+        let (a, b, c) = (matrix(2, 0), matrix(3, 1), matrix(4, 2));
+        let mut history = history(&[a.clone(), b.clone(), c.clone()]);
+        let mut current = c.clone();
+        let mut config = ConfigState::default();
+
+        history.undo(&mut current, &mut config);
+        assert!(current == b);
+        assert_eq!(config.colors_count, 3);
+        history.undo(&mut current, &mut config);
+        history.undo(&mut current, &mut config);
+        assert!(current == a);
+        assert_eq!(config.colors_count, 2);
+        history.redo(&mut current, &mut config);
+        history.redo(&mut current, &mut config);
+        history.redo(&mut current, &mut config);
+        assert!(current == c);
+        assert_eq!(history.entries.len(), 3);
+    }
+
+    #[test]
+    fn unchanged_matrices_are_recorded_once() {
+        let a = matrix(2, 0);
+        let history = history(&[a.clone(), a.clone(), a]);
+        assert_eq!(history.entries.len(), 1);
+    }
+
+    #[test]
+    fn editing_after_undo_drops_the_redo_states() {
+        let (a, b, c, d) = (matrix(2, 0), matrix(2, 1), matrix(2, 2), matrix(2, 3));
+        let mut history = history(&[a, b.clone(), c]);
+        let mut current = history.current().unwrap().clone();
+        let mut config = ConfigState::default();
+
+        history.undo(&mut current, &mut config);
+        assert!(current == b);
+        history.record(&d);
+        current = d.clone();
+        history.redo(&mut current, &mut config);
+        assert!(current == d);
+        assert_eq!(history.entries.iter().map(|entry| entry.id).collect::<Vec<_>>(), [0, 1, 3]);
+    }
+
+    #[test]
+    fn undo_keeps_an_unrecorded_edit_for_redo() {
+        let (a, b) = (matrix(2, 0), matrix(2, 1));
+        let mut history = history(std::slice::from_ref(&a));
+        let mut current = b.clone();
+        let mut config = ConfigState::default();
+
+        history.undo(&mut current, &mut config);
+        assert!(current == a);
+        history.redo(&mut current, &mut config);
+        assert!(current == b);
+    }
+
+    #[test]
+    fn the_oldest_entries_are_evicted() {
+        let extra = 5;
+        let matrices = (0..HISTORY_LEN + extra).map(|seed| matrix(2, seed as u64)).collect::<Vec<_>>();
+        let history = history(&matrices);
+        assert_eq!(history.entries.len(), HISTORY_LEN);
+        assert_eq!(history.entries.front().unwrap().id, extra as u64);
+        assert_eq!(history.cursor, HISTORY_LEN - 1);
+        assert!(history.current() == matrices.last());
+    }
+
+    #[test]
+    fn a_drag_is_recorded_as_one_entry() {
+        let mut world = World::new();
+        world.init_resource::<MatrixHistory>();
+        world.init_resource::<ButtonInput<MouseButton>>();
+        world.insert_resource(matrix(2, 0));
+        world.run_system_once(record_matrix_history).unwrap();
+
+        world.resource_mut::<ButtonInput<MouseButton>>().press(MouseButton::Left);
+        for seed in 1..5 {
+            world.insert_resource(matrix(2, seed));
+            world.run_system_once(record_matrix_history).unwrap();
+        }
+        assert_eq!(world.resource::<MatrixHistory>().entries.len(), 1);
+
+        world.resource_mut::<ButtonInput<MouseButton>>().release(MouseButton::Left);
+        world.run_system_once(record_matrix_history).unwrap();
+        let history = world.resource::<MatrixHistory>();
+        assert_eq!(history.entries.len(), 2);
+        assert!(history.current() == Some(&matrix(2, 4)));
+    }
+}
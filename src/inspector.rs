@@ -0,0 +1,159 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::{EguiContexts, egui, input::EguiWantsInput};
+use glam::DVec2;
+use rayon::prelude::*;
+
+use crate::{
+    brush::{Brush, BrushMode},
+    config::ConfigState,
+    palette::Palette,
+    physics::{params::PhysicsParams, physics::ParticlePhysics},
+    render,
+};
+
+// how close to a particle a click has to land, in screen pixels
+const PICK_PIXELS: f32 = 8.0;
+// presses that move further than this are pans rather than clicks
+const CLICK_PIXELS: f32 = 4.0;
+
+// the particle picked by clicking on it
+#[derive(Default, Resource)]
+pub struct Inspector {
+    selected: Option<usize>,
+    follow: bool, // keep the view centered on the selection
+    press: Option<Vec2>, // where the left button went down
+    generation: u64, // the bodies' generation the selection was made in
+}
+
+// a left click in the world selects the nearest particle, or nothing
+pub fn pick_particle(
+    mut inspector: ResMut<Inspector>,
+    brush: Res<Brush>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    config: Res<ConfigState>,
+    egui_input: Res<EguiWantsInput>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    params: Res<PhysicsParams>,
+    physics: Res<ParticlePhysics>,
+    window: Single<&Window, With<PrimaryWindow>>,
+) {
+    // the left button belongs to the brush tools
+    if brush.mode != BrushMode::Pan {
+        inspector.press = None;
+        return;
+    }
+    let Some(cursor) = window.cursor_position() else { return };
+    if mouse.just_pressed(MouseButton::Left) {
+        inspector.press = (!egui_input.wants_any_pointer_input()).then_some(cursor);
+    }
+    if !mouse.just_released(MouseButton::Left) { return }
+    let Some(press) = inspector.press.take() else { return };
+    if press.distance(cursor) > CLICK_PIXELS { return }
+
+    let Ok((camera, camera_transform)) = camera.single() else { return };
+    let (Ok(world), Ok(edge)) = (
+        camera.viewport_to_world_2d(camera_transform, cursor),
+        camera.viewport_to_world_2d(camera_transform, cursor + Vec2::X * PICK_PIXELS),
+    ) else { return };
+    let pos = render::sim_position(world, config.half_side, config.view_center, params.boundary);
+    let reach = world.distance(edge) as f64 / (2.0 * config.half_side as f64);
+    inspector.selected = physics.particles().positions
+        .par_iter()
+        .enumerate()
+        .map(|(ix, p)| (ix, params.boundary.offset(pos, *p).length_squared()))
+        .filter(|(_, dist_sqrd)| *dist_sqrd <= reach * reach)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(ix, _)| ix);
+}
+
+// centering the view on the particle wraps the torus around it, so the
+// camera only has to stay put while the particle crosses an edge
+pub fn follow_particle(
+    mut config: ResMut<ConfigState>,
+    mut inspector: ResMut<Inspector>,
+    physics: Res<ParticlePhysics>,
+) {
+    let particles = physics.particles();
+    // removals and respawns hand indices to other bodies, so the selection
+    // would quietly move to a different particle
+    if particles.generation() != inspector.generation || inspector.selected.is_some_and(|ix| ix >= particles.len()) {
+        inspector.selected = None;
+    }
+    inspector.generation = particles.generation();
+    let Some(ix) = inspector.selected else { return };
+    if !inspector.follow { return }
+    let center = particles.positions[ix];
+    if config.view_center != center {
+        config.view_center = center;
+    }
+    config.focus_ix = ix;
+}
+
+pub fn inspector_ui(
+    mut gui: EguiContexts,
+    mut inspector: ResMut<Inspector>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    config: Res<ConfigState>,
+    palette: Res<Palette>,
+    params: Res<PhysicsParams>,
+    physics: Res<ParticlePhysics>,
+) {
+    let Some(ix) = inspector.selected else { return };
+    let particles = physics.particles();
+    if ix >= particles.len() { return }
+    let Ok(ctx) = gui.ctx_mut() else { return };
+    let body = particles.get(ix);
+
+    // ring the particle where it's drawn
+    let world = render::world_position(body.position, config.half_side, config.view_center, params.boundary);
+    if let Ok((camera, camera_transform)) = camera.single()
+        && let Ok(center) = camera.world_to_viewport(camera_transform, world.extend(0.0))
+    {
+        ctx.layer_painter(egui::LayerId::background()).circle_stroke(
+            egui::pos2(center.x, center.y),
+            PICK_PIXELS,
+            (1.5, egui::Color32::WHITE),
+        );
+    }
+
+    let vec = |v: DVec2| format!("{:.4}, {:.4}", v.x, v.y);
+    let mut open = true;
+    egui::Window::new("Particle")
+        .open(&mut open)
+        .resizable(false)
+        .default_pos([config.panel_width + 40.0, 40.0])
+        .show(ctx, |ui| {
+            egui::Grid::new("inspector").num_columns(2).show(ui, |ui| {
+                ui.label("Index");
+                ui.label(ix.to_string());
+                ui.end_row();
+                ui.label("Color");
+                ui.horizontal(|ui| {
+                    let (rect, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                    ui.painter().rect_filled(rect, 2.0, palette.egui_color(body.color));
+                    ui.label(body.color.to_string());
+                });
+                ui.end_row();
+                ui.label("Position");
+                ui.label(vec(body.position));
+                ui.end_row();
+                ui.label("Velocity");
+                ui.label(vec(body.velocity));
+                ui.end_row();
+                ui.label("Speed");
+                ui.label(format!("{:.4}", body.velocity.length()));
+                ui.end_row();
+                ui.label("Force").on_hover_text("Net force from the last evaluation");
+                ui.label(physics.force(ix).map_or("-".into(), vec));
+                ui.end_row();
+                ui.label("Neighbors").on_hover_text("Particles within the largest interaction radius");
+                ui.label(physics.neighbor_count(ix).map_or("-".into(), |n| n.to_string()));
+                ui.end_row();
+            });
+            ui.checkbox(&mut inspector.follow, "Follow");
+        });
+    if !open {
+        inspector.selected = None;
+        inspector.follow = false;
+    }
+}
@@ -1,32 +1,59 @@
+// bevy systems routinely take many params
+#![allow(clippy::too_many_arguments)]
+
 use bevy::{
     dev_tools::fps_overlay::{FpsOverlayConfig, FpsOverlayPlugin, FrameTimeGraphConfig},
     input::common_conditions::input_just_pressed,
     prelude::*,
-    window::{PrimaryWindow, WindowMode},
+    window::PrimaryWindow,
 };
 use bevy_egui::{EguiPlugin, EguiPrimaryContextPass};
-use bevy_pancam::{PanCam, PanCamPlugin};
+use bevy_pancam::{PanCam, PanCamPlugin, PanCamSystems};
+use brush::Brush;
+use cluster_log::ClusterLog;
+use clusters::ClusterTracker;
+use clap::Parser as _;
+use cli::Args;
 use config::ConfigState;
-use glam::DVec2;
-use palette::Palette;
+use history::MatrixHistory;
+use inspector::Inspector;
+use palette::{Palette, random_color};
 use physics::{
     bodies::PointBody,
     forces::ForceMatrix,
     physics::ParticlePhysics,
+    plugin::{ParticlePhysicsPlugin, PhysicsSystems},
+    species::SpeciesTable,
 };
-use rand::Rng as _;
+use presets::{Preset, PresetLibrary};
+use rand::Rng;
+use render::ParticleRenderPlugin;
+use rng::SimRng;
+use snapshot::{SaveState, SnapshotState};
+use stats::Statistics;
+use toasts::Toasts;
 
 use crate::providers::positioners::get_position;
 
+mod brush;
+mod cli;
+mod cluster_log;
+mod clusters;
 mod config;
+mod headless;
+mod history;
+mod inspector;
 mod palette;
 mod physics;
+mod presets;
 mod providers;
-// mod snapshot;
+mod render;
+mod rng;
+mod snapshot;
+mod stats;
+mod toasts;
 mod ui;
 
-const RADIUS: f32 = 0.5833334; // 0.5 * (0.5 + 2.0 / 3.0);
-
 #[derive(States, PartialEq, Eq, Debug, Clone, Hash, Default)]
 enum ShowUi {
     #[default]
@@ -41,14 +68,29 @@ enum AppState {
     Paused,
 }
 
-fn main() {
+fn main() -> AppExit {
+    let args = Args::parse();
+    let config = args.config();
+    let params = args.physics();
+    if args.headless {
+        return headless::run(&args, config, params);
+    }
+    let (presets, snapshot) = match start_files(&args) {
+        Ok(files) => files,
+        Err(err) => {
+            eprintln!("{err}");
+            return AppExit::error();
+        },
+    };
+
     App::new()
         .insert_resource(ClearColor(Color::srgb(0.025, 0.025, 0.025)))
         .add_plugins((
             DefaultPlugins
                 .set(WindowPlugin {
                     primary_window: Some(Window {
-                        mode: WindowMode::BorderlessFullscreen(MonitorSelection::Primary),
+                        mode: args.window_mode(),
+                        resolution: args.size.into(),
                         ..default()
                     }),
                     ..default()
@@ -62,43 +104,104 @@ fn main() {
                     ..Default::default()
                 },
             },
-            PanCamPlugin::default(),
+            PanCamPlugin,
+            ParticlePhysicsPlugin,
+            ParticleRenderPlugin,
+        ))
+        .insert_resource(config)
+        .insert_resource(params)
+        .init_resource::<MatrixHistory>()
+        .insert_resource(presets)
+        .insert_resource(snapshot)
+        .init_resource::<Brush>()
+        .init_resource::<Inspector>()
+        .init_resource::<Statistics>()
+        .init_resource::<ClusterTracker>()
+        .init_resource::<ClusterLog>()
+        .init_resource::<Toasts>()
+        .insert_state(match args.paused {
+            true => AppState::Paused,
+            false => AppState::Running,
+        })
+        .insert_state(match args.hide_ui {
+            true => ShowUi::No,
+            false => ShowUi::Yes,
+        })
+        .configure_sets(FixedUpdate, PhysicsSystems::StepSimulation.run_if(in_state(AppState::Running)))
+        .add_systems(FixedUpdate, (
+            brush::push_brush
+                .after(PhysicsSystems::Prepare)
+                .before(PhysicsSystems::StepSimulation)
+                .run_if(in_state(AppState::Running)),
+            respawn_absorbed.in_set(PhysicsSystems::Writeback),
         ))
-        .init_state::<AppState>()
-        .init_state::<ShowUi>()
         .add_systems(Startup, setup)
         .add_systems(Update, (
+            snapshot::save_snapshot,
+            snapshot::load_snapshot.before(match_body_count),
+            presets::save_preset,
+            presets::load_preset.before(match_body_count),
             match_body_count,
-            update_bodies.run_if(in_state(AppState::Running)),
-            palette::update_palette,
+            brush::update_brush.before(PanCamSystems),
+            brush::paint_brush.after(brush::update_brush).before(match_body_count),
+            inspector::pick_particle,
+            inspector::follow_particle.after(match_body_count),
+            stats::sample_statistics.after(match_body_count).run_if(in_state(AppState::Running)),
+            clusters::detect_clusters.after(match_body_count).run_if(in_state(AppState::Running)),
+            palette::update_palette.before(match_body_count),
             ui::toggle_running.run_if(input_just_pressed(KeyCode::Space)),
             ui::toggle_visible.run_if(input_just_pressed(KeyCode::Escape)),
             ui::negate_forces.run_if(input_just_pressed(KeyCode::KeyN)),
+            history::undo_redo.run_if(input_just_pressed(KeyCode::KeyZ)).before(history::record_matrix_history),
+            history::record_matrix_history.before(match_body_count),
+            snapshot::request_save.run_if(input_just_pressed(KeyCode::F5)),
+            snapshot::request_load.run_if(input_just_pressed(KeyCode::F9)),
+        ))
+        .add_systems(EguiPrimaryContextPass, (
+            ui::ui_system.run_if(in_state(ShowUi::Yes)),
+            stats::stats_ui.run_if(in_state(ShowUi::Yes)),
+            clusters::clusters_ui.run_if(in_state(ShowUi::Yes)),
+            cluster_log::cluster_log_ui.run_if(in_state(ShowUi::Yes)),
+            brush::draw_brush,
+            inspector::inspector_ui,
         ))
-        .add_systems(EguiPrimaryContextPass, ui::ui_system.run_if(in_state(ShowUi::Yes)))
-        .run();
+        .run()
+}
+
+// a preset or snapshot from the command line is loaded by the usual systems on
+// the first frame, reading it here first just fails before a window opens
+fn start_files(args: &Args) -> Result<(PresetLibrary, SnapshotState), String> {
+    let mut presets = PresetLibrary::default();
+    let mut snapshot = SnapshotState::default();
+    if let Some(name) = &args.preset {
+        let path = presets.find(name);
+        Preset::read(&path).map_err(|err| format!("failed to read preset {}: {err}", path.display()))?;
+        presets.request_load(path);
+    }
+    if let Some(path) = &args.snapshot {
+        SaveState::read(path).map_err(|err| format!("failed to read snapshot {}: {err}", path.display()))?;
+        snapshot.path = path.display().to_string();
+        snapshot.load_requested = true;
+    }
+    Ok((presets, snapshot))
 }
 
 fn setup(
     mut commands: Commands,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
+    mut config: ResMut<ConfigState>,
+    mut species: ResMut<SpeciesTable>,
     window: Single<&Window, With<PrimaryWindow>>,
 ) {
 
     let side = f32::min(window.height(), window.width());
-    let mesh = meshes.add(Circle::new(RADIUS));
-    let config = ConfigState {
-        half_side: side * 0.5,
-        body_mesh: Some(mesh.clone()),
-        ..default()
-    };
+    config.half_side = side * 0.5;
     let colors_count = config.colors_count as usize;
+    let mut rng = SimRng::new(config.seed);
 
-    commands.insert_resource(ForceMatrix::new(colors_count, config.force_matrix_option));
-    commands.insert_resource(Palette::new(&mut materials, colors_count));
-    commands.insert_resource(ParticlePhysics::default());
-    commands.insert_resource(config);
+    commands.insert_resource(ForceMatrix::new(colors_count, config.force_matrix_option, &mut rng));
+    commands.insert_resource(Palette::new(colors_count));
+    species.grow(colors_count);
+    commands.insert_resource(rng);
 
     commands.spawn((
         Camera2d,
@@ -112,97 +215,50 @@ fn setup(
 }
 
 fn match_body_count(
-    mut commands: Commands,
     mut config: ResMut<ConfigState>,
-    palette: Res<Palette>,
-    query: Query<Entity, With<PointBody>>,
+    mut force_matrix: ResMut<ForceMatrix>,
+    mut physics: ResMut<ParticlePhysics>,
+    mut rng: ResMut<SimRng>,
 ) {
+    // reseeding right before the rebuild keeps anything else drawing from the
+    // rng in between, like absorbed respawns, out of the restarted state
+    if config.restart {
+        config.restart = false;
+        config.reset_bodies = true;
+        rng.reseed(config.seed);
+        *force_matrix = ForceMatrix::new(config.colors_count as usize, config.force_matrix_option, &mut *rng);
+    }
+    let particles = physics.particles_mut();
+    let target_size = config.bodies_count as usize;
     if config.reset_bodies {
         config.reset_bodies = false;
-        for entity in query.iter() {
-            commands.entity(entity).despawn();
-        }
-        for _ in 0..config.bodies_count {
-            build_particle(&mut commands, &config, &palette);
-        }
-    } else {
-        let mut current_size = query.count();
-        let target_size = config.bodies_count as usize;
-        if current_size == target_size { return }
-
-        while current_size < target_size {
-            current_size += 1;
-            build_particle(&mut commands, &config, &palette);
-        }
-        let mut rng = rand::rng();
-        while current_size > target_size {
-            let rix = rng.random::<u64>() as usize % current_size;
-            if let Some(entity) = query.iter().nth(rix) {
-                commands.entity(entity).despawn();
-                current_size -= 1;
-            } else {
-                panic!("stuck!");
-            }
-        }
+        particles.clear();
     }
-}
-
-const CHILD_OFFSETS: [DVec2; 4] = [
-    DVec2::new( 1.5,  0.5), //right
-    DVec2::new( 0.5,  1.5), //top
-    DVec2::new(-0.5,  0.5), // left
-    DVec2::new( 0.5, -0.5), // bottom
-];
-
-fn build_particle(commands: &mut Commands, config: &ConfigState, palette: &Palette) {
-    let color = palette.random_ix();
-    let position = get_position(&config.position_option);
-    let body = PointBody::new(color, position);
-    let mut entity = commands.spawn((
-        Mesh2d(config.body_mesh.clone().unwrap()),
-        MeshMaterial2d(palette.get(color).clone()),
-        get_transform(vec3(&body.position), &config),
-        body,
-    ));
-    entity.with_children(|commands| {
-        for offset in &CHILD_OFFSETS {
-            commands.spawn((
-                Mesh2d(config.body_mesh.clone().unwrap()),
-                MeshMaterial2d(palette.white().clone()),
-                get_transform(vec3(offset), &config),
-            ));
+    if particles.len() < target_size {
+        particles.reserve(target_size - particles.len());
+        while particles.len() < target_size {
+            particles.push(random_body(&config, &mut *rng));
         }
-    });
+    }
+    // each removal is O(1), so large deltas are linear in the delta
+    while particles.len() > target_size {
+        let rix = rng.random::<u64>() as usize % particles.len();
+        particles.swap_remove(rix);
+    }
 }
 
-fn update_bodies(
+// puts bodies that left through an absorbing edge back in via the positioner
+fn respawn_absorbed(
     mut physics: ResMut<ParticlePhysics>,
-    mut query: Query<(&mut Transform, &mut PointBody)>,
+    mut rng: ResMut<SimRng>,
     config: Res<ConfigState>,
-    force_matrix: Res<ForceMatrix>,
-    time: Res<Time<Fixed>>,
 ) {
-    let bodies = query
-        .iter()
-        .map(|(_, body)| body)
-        .collect::<Vec<_>>();
-    let forces = physics.get_forces(&bodies, &force_matrix);
-    for (i, (mut transform, mut body)) in query.iter_mut().enumerate() {
-        body.step(forces[i], time.delta_secs_f64());
-        *transform = get_transform(vec3(&body.position), &config);
-    }
-}
-
-#[inline]
-fn vec3(position: &DVec2) -> Vec3 {
-    Vec3 {
-        x: position.x as f32 * 2.0 - 1.0,
-        y: position.y as f32 * 2.0 - 1.0,
-        z: 0.0
-    }
+    physics.respawn_absorbed(|| get_position(&config.position_option, &mut *rng));
 }
 
-#[inline]
-fn get_transform(pos: Vec3, config: &ConfigState) -> Transform {
-    Transform::from_translation(pos * config.half_side)
+// draws the color then the position, so headless and windowed runs agree
+fn random_body<R: Rng + ?Sized>(config: &ConfigState, rng: &mut R) -> PointBody {
+    let color = random_color(config.colors_count as usize, rng);
+    let position = get_position(&config.position_option, rng);
+    PointBody::new(color, position)
 }
@@ -1,66 +1,93 @@
-use bevy::prelude::*;
-use rand::random;
-use crate::{physics::bodies::PointBody, config::ConfigState};
+use bevy::{color::ColorToPacked, prelude::*};
+use bevy_egui::egui::Color32;
+use rand::Rng;
+use crate::{physics::{physics::ParticlePhysics, species::SpeciesTable}, config::ConfigState, rng::SimRng};
 
 #[derive(Resource)]
 pub struct Palette {
-    data: Vec<Handle<ColorMaterial>>,
+    data: Vec<Color>,
     size: usize,
-    white: Handle<ColorMaterial>,
+    white: Color,
 }
 
 impl Palette {
-    pub fn new(
-        materials: &mut Assets<ColorMaterial>,
-        size: usize,
-    ) -> Self {
+    pub fn new(size: usize) -> Self {
         Self {
             data: (0..size)
-                .into_iter()
                 .map(|i| {
                     let hue = (i as f32 / size as f32) * 360.0;
-                    materials.add(ColorMaterial::from(Color::hsl(hue, 1.0, 0.5)))
+                    Color::hsl(hue, 1.0, 0.5)
                 })
                 .collect(),
-            white: materials.add(ColorMaterial::from(Color::WHITE)),
+            white: Color::WHITE,
             size,
         }
     }
 
-    pub fn get(&self, i: usize) -> &Handle<ColorMaterial> {
-        &self.data[i]
+    // a palette of the given srgb colors
+    pub fn from_rgb(rgb: &[[u8; 3]]) -> Self {
+        Self {
+            data: rgb.iter().map(|[r, g, b]| Color::srgb_u8(*r, *g, *b)).collect(),
+            white: Color::WHITE,
+            size: rgb.len(),
+        }
+    }
+
+    pub fn to_rgb(&self) -> Vec<[u8; 3]> {
+        self.data
+            .iter()
+            .map(|color| {
+                let [r, g, b, _] = color.to_srgba().to_u8_array();
+                [r, g, b]
+            })
+            .collect()
+    }
+
+    // falls back to white for colors the palette hasn't caught up with yet
+    pub fn get(&self, i: usize) -> Color {
+        self.data.get(i).copied().unwrap_or(self.white)
     }
 
-    pub fn random_ix(&self) -> usize {
-        random::<u64>() as usize % self.size
+    // the same color for painting in the ui
+    pub fn egui_color(&self, i: usize) -> Color32 {
+        let [r, g, b, _] = self.get(i).to_srgba().to_u8_array();
+        Color32::from_rgb(r, g, b)
     }
 
-    pub fn white(&self) -> &Handle<ColorMaterial> {
-        &self.white
+    pub fn len(&self) -> usize {
+        self.size
     }
+
+    pub fn random_ix<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        random_color(self.size, rng)
+    }
+
+    pub fn white(&self) -> Color {
+        self.white
+    }
+}
+
+#[inline]
+pub fn random_color<R: Rng + ?Sized>(size: usize, rng: &mut R) -> usize {
+    rng.random::<u64>() as usize % size
 }
 
 pub fn update_palette(
-    mut materials: ResMut<Assets<ColorMaterial>>,
     mut palette: ResMut<Palette>,
-    mut query: Query<(&mut MeshMaterial2d<ColorMaterial>, &mut PointBody)>,
+    mut physics: ResMut<ParticlePhysics>,
+    mut rng: ResMut<SimRng>,
+    mut species: ResMut<SpeciesTable>,
     config: Res<ConfigState>,
 ) {
     let size = config.colors_count as usize;
     if size != palette.size {
         // re-init palette
-        *palette = Palette::new(&mut materials, size);
-        // reassign colors
-        for (mut hndl, mut body) in query.iter_mut() {
-            match body.color.cmp(&size) {
-                std::cmp::Ordering::Less => {
-                    **hndl = palette.get(body.color).clone();
-                },
-                _ => {
-                    let color = palette.random_ix();
-                    body.color = color;
-                    **hndl = palette.get(color).clone();
-                },
+        *palette = Palette::new(size);
+        species.grow(size);
+        // reassign colors that no longer exist
+        for color in physics.particles_mut().colors.iter_mut() {
+            if *color >= size {
+                *color = palette.random_ix(&mut *rng);
             }
         }
     }
@@ -1,9 +1,9 @@
-use bevy::prelude::Component;
 use glam::DVec2;
+use serde::{Deserialize, Serialize};
 
-const DRAG_HALFLIFE: f64 = 23.255813953488374; // 1.0 / 0.043
-
-#[derive(Clone, Copy, Component, Debug, Default)]
+// a single body, as spawned and saved; the simulation itself
+// keeps bodies in `Particles`
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
 pub struct PointBody {
     pub color: usize,
     pub position: DVec2,
@@ -20,14 +20,4 @@ impl PointBody {
         }
     }
 
-    #[inline]
-    pub fn step(&mut self, force: DVec2, dt: f64) {
-        // degrade velocity before adding force
-        self.velocity *= 0.5f64.powf(DRAG_HALFLIFE * dt);
-        self.velocity += force * dt;
-        // update position and wrap in space
-        self.position += self.velocity * dt;
-        self.position = self.position.rem_euclid(DVec2::ONE);
-    }
-
 }
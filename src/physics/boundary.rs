@@ -0,0 +1,90 @@
+use glam::DVec2;
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+use std::fmt::{Debug, Display, Formatter, Result};
+
+// what happens at the edges of the unit square
+#[derive(Clone, Copy, Default, Deserialize, EnumIter, PartialEq, Serialize)]
+pub enum BoundaryMode {
+    // a torus, leaving one edge enters at the opposite one
+    #[default]
+    Wrap,
+    // walls that bounce particles back in
+    Reflect,
+    // particles leaving the square are respawned by the positioner
+    Absorb,
+    // an unbounded plane, the square is only where particles start
+    Open,
+}
+
+impl Debug for BoundaryMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", match &self {
+            BoundaryMode::Wrap => "Wrap",
+            BoundaryMode::Reflect => "Reflect",
+            BoundaryMode::Absorb => "Absorb",
+            BoundaryMode::Open => "Open",
+        })
+    }
+}
+
+impl Display for BoundaryMode {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl BoundaryMode {
+
+    #[inline]
+    pub fn wraps(self) -> bool {
+        self == BoundaryMode::Wrap
+    }
+
+    #[inline]
+    pub fn is_bounded(self) -> bool {
+        self != BoundaryMode::Open
+    }
+
+    // the offset from `from` to `to`, the shortest one on the torus when wrapping
+    #[inline]
+    pub fn offset(self, from: DVec2, to: DVec2) -> DVec2 {
+        match self {
+            BoundaryMode::Wrap => (to - from + 0.5).rem_euclid(DVec2::ONE) - 0.5,
+            _ => to - from,
+        }
+    }
+
+    // keeps a freshly integrated body inside the square, if the mode does that
+    #[inline]
+    pub fn confine(self, position: &mut DVec2, velocity: &mut DVec2) {
+        match self {
+            BoundaryMode::Wrap => *position = position.rem_euclid(DVec2::ONE),
+            BoundaryMode::Reflect => {
+                reflect(&mut position.x, &mut velocity.x);
+                reflect(&mut position.y, &mut velocity.y);
+            },
+            BoundaryMode::Absorb | BoundaryMode::Open => {},
+        }
+    }
+
+    // whether a body at `position` has left through an absorbing edge
+    #[inline]
+    pub fn absorbs(self, position: DVec2) -> bool {
+        self == BoundaryMode::Absorb
+            && !(position.cmpge(DVec2::ZERO).all() && position.cmplt(DVec2::ONE).all())
+    }
+}
+
+// mirror a coordinate back into [0, 1], clamping in case it overshot a whole side
+#[inline]
+fn reflect(position: &mut f64, velocity: &mut f64) {
+    if *position < 0.0 {
+        *position = -*position;
+        *velocity = -*velocity;
+    } else if *position > 1.0 {
+        *position = 2.0 - *position;
+        *velocity = -*velocity;
+    }
+    *position = position.clamp(0.0, 1.0);
+}
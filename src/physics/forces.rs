@@ -1,36 +1,111 @@
-use std::fmt::{Debug, Display, Formatter, Result};
+use std::fmt::{self, Debug, Display, Formatter};
 
 use arboard::Clipboard;
 use bevy::prelude::Resource;
-use bevy_egui::egui::{self, DragValue, Ui};
-use rand::random;
+use bevy_egui::egui::{self, Color32, DragValue, Ui};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use strum::{IntoEnumIterator, EnumIter};
-use crate::config::ConfigState;
+use crate::{config::ConfigState, palette::Palette, rng::SimRng, toasts::Toasts};
 
-#[derive(Clone, Copy, EnumIter, PartialEq)]
+#[derive(Clone, Copy, Deserialize, EnumIter, PartialEq, Serialize)]
 pub enum ForceMatrixType {
     Chains(ChainsForceMatrix),
     Random(RandomForceMatrix),
     Snakes(SnakeForceMatrix),
-    // Symmetry(SymmetryForceMatrix),
+    Symmetry(SymmetryForceMatrix),
+    Antisymmetry(AntisymmetryForceMatrix),
+    Circulant(CirculantForceMatrix),
+    Blocks(BlockForceMatrix),
+    Sparse(SparseForceMatrix),
+    Gaussian(GaussianForceMatrix),
     Zeros(ZeroForceMatrix),
     Ones(IdentForceMatrix),
 }
 
 impl Debug for ForceMatrixType {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{}", match &self {
             ForceMatrixType::Chains(_) => "Chains",
             ForceMatrixType::Random(_) => "Random",
             ForceMatrixType::Snakes(_) => "Snakes",
+            ForceMatrixType::Symmetry(_) => "Symmetric",
+            ForceMatrixType::Antisymmetry(_) => "Antisymmetric",
+            ForceMatrixType::Circulant(_) => "Circulant",
+            ForceMatrixType::Blocks(_) => "Blocks",
+            ForceMatrixType::Sparse(_) => "Sparse",
+            ForceMatrixType::Gaussian(_) => "Gaussian",
             ForceMatrixType::Zeros(_) => "Zeros",
             ForceMatrixType::Ones(_) => "Ones",
         })
     }
 }
 
+impl ForceMatrixType {
+    fn generate<R: Rng + ?Sized>(self, w: usize, rng: &mut R) -> Vec<f64> {
+        match self {
+            ForceMatrixType::Chains(p) => p.generate(w, rng),
+            ForceMatrixType::Random(p) => p.generate(w, rng),
+            ForceMatrixType::Snakes(p) => p.generate(w, rng),
+            ForceMatrixType::Symmetry(p) => p.generate(w, rng),
+            ForceMatrixType::Antisymmetry(p) => p.generate(w, rng),
+            ForceMatrixType::Circulant(p) => p.generate(w, rng),
+            ForceMatrixType::Blocks(p) => p.generate(w, rng),
+            ForceMatrixType::Sparse(p) => p.generate(w, rng),
+            ForceMatrixType::Gaussian(p) => p.generate(w, rng),
+            ForceMatrixType::Zeros(p) => p.generate(w, rng),
+            ForceMatrixType::Ones(p) => p.generate(w, rng),
+        }
+    }
+
+    // true if both are the same kind of matrix, whatever their settings
+    fn same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    pub fn params_ui(&mut self, ui: &mut Ui) {
+        match self {
+            ForceMatrixType::Symmetry(p) => {
+                labeled(ui, "Amplitude", DragValue::new(&mut p.amplitude).speed(0.01).range(0.0..=10.0));
+            },
+            ForceMatrixType::Antisymmetry(p) => {
+                labeled(ui, "Amplitude", DragValue::new(&mut p.amplitude).speed(0.01).range(0.0..=10.0));
+                labeled(ui, "Diagonal", DragValue::new(&mut p.diagonal).speed(0.01));
+            },
+            ForceMatrixType::Circulant(p) => {
+                // one value per offset from the diagonal, -2 through 2
+                ui.horizontal(|ui| {
+                    for force in &mut p.offsets {
+                        ui.add(DragValue::new(force).speed(0.01));
+                    }
+                }).response.on_hover_text("Force towards the colors 2 before through 2 after");
+            },
+            ForceMatrixType::Blocks(p) => {
+                labeled(ui, "Blocks", DragValue::new(&mut p.blocks).range(1..=MAX_BLOCKS));
+                labeled(ui, "Within", DragValue::new(&mut p.within).speed(0.01));
+                labeled(ui, "Between", DragValue::new(&mut p.between).speed(0.01));
+            },
+            ForceMatrixType::Sparse(p) => {
+                labeled(ui, "Density", DragValue::new(&mut p.density).speed(0.01).range(0.0..=1.0));
+            },
+            ForceMatrixType::Gaussian(p) => {
+                labeled(ui, "Mean", DragValue::new(&mut p.mean).speed(0.01));
+                labeled(ui, "Std Dev", DragValue::new(&mut p.std_dev).speed(0.01).range(0.0..=10.0));
+            },
+            _ => {},
+        }
+    }
+}
+
+fn labeled(ui: &mut Ui, label: &str, value: DragValue) {
+    ui.horizontal(|ui| {
+        ui.add(value);
+        ui.label(label);
+    });
+}
+
 impl Display for ForceMatrixType {
-    fn fmt(&self, f: &mut Formatter) -> Result {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         Debug::fmt(&self, f)
     }
 }
@@ -40,69 +115,192 @@ enum ForceShiftType {
     Row,
 }
 
-#[derive(Clone, Resource)]
+// which of the per-pair matrices the ui is editing
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MatrixLayer {
+    #[default]
+    Force,
+    MaxRadius,
+    MinRadius,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClipboardFormat {
+    Csv,
+    Json,
+}
+
+// colors are counted in a u8
+const MAX_COLORS: usize = u8::MAX as usize;
+
+// the radius matrices scale the global interaction radius and repulsion distance
+const DEFAULT_RADIUS_SCALE: f64 = 1.0;
+pub const MIN_RADIUS_SCALE: f64 = 0.1;
+pub const MAX_RADIUS_SCALE: f64 = 4.0;
+
+#[derive(Clone, Deserialize, PartialEq, Resource, Serialize)]
 pub struct ForceMatrix {
     data: Vec<f64>,
+    // older snapshots predate these, see `fill_radii`
+    #[serde(default)]
+    max_radius: Vec<f64>,
+    #[serde(default)]
+    min_radius: Vec<f64>,
     color_count: usize,
     matrix_type: ForceMatrixType,
 }
 
 impl ForceMatrix {
 
-    pub fn new(color_count: usize, matrix_type: ForceMatrixType) -> Self {
+    pub fn new<R: Rng + ?Sized>(color_count: usize, matrix_type: ForceMatrixType, rng: &mut R) -> Self {
         assert!(color_count > 0);
-        let data = (0..color_count * color_count)
-            .into_iter()
-            .map(|i| {
-                let x = i % color_count;
-                let y = i / color_count;
-                let f = match matrix_type {
-                    ForceMatrixType::Chains(p) => p.force(x, y, color_count),
-                    ForceMatrixType::Random(p) => p.force(x, y, color_count),
-                    ForceMatrixType::Snakes(p) => p.force(x, y, color_count),
-                    ForceMatrixType::Zeros(p) => p.force(x, y, color_count),
-                    ForceMatrixType::Ones(p) => p.force(x, y, color_count),
-                };
-                f.into()
-            })
-            .collect::<Vec<_>>();
+        let data = matrix_type.generate(color_count, rng);
+
+        let radii = vec![DEFAULT_RADIUS_SCALE; data.len()];
+        Self { data, max_radius: radii.clone(), min_radius: radii, color_count, matrix_type }
+    }
+
+    #[inline]
+    pub fn color_count(&self) -> usize {
+        self.color_count
+    }
+
+    #[inline]
+    pub fn matrix_type(&self) -> ForceMatrixType {
+        self.matrix_type
+    }
+
+    // true if every matrix is a full square matrix of `color_count` colors,
+    // the forces are finite and the radii are in range
+    pub fn is_valid(&self) -> bool {
+        // a hostile color count can't overflow the size
+        let Some(size) = self.color_count.checked_mul(self.color_count) else { return false };
+        let radius_ok = |r: &f64| (MIN_RADIUS_SCALE..=MAX_RADIUS_SCALE).contains(r);
+        self.color_count > 0
+            && self.data.len() == size
+            && self.max_radius.len() == size
+            && self.min_radius.len() == size
+            && self.data.iter().all(|f| f.is_finite())
+            && self.max_radius.iter().chain(&self.min_radius).all(radius_ok)
+    }
+
+    // give matrices saved without radii the default ones
+    pub fn fill_radii(&mut self) {
+        if self.max_radius.is_empty() {
+            self.max_radius = vec![DEFAULT_RADIUS_SCALE; self.data.len()];
+        }
+        if self.min_radius.is_empty() {
+            self.min_radius = vec![DEFAULT_RADIUS_SCALE; self.data.len()];
+        }
+    }
 
-        Self { data, color_count, matrix_type }
+    #[inline]
+    fn layer(&self, layer: MatrixLayer) -> &Vec<f64> {
+        match layer {
+            MatrixLayer::Force => &self.data,
+            MatrixLayer::MaxRadius => &self.max_radius,
+            MatrixLayer::MinRadius => &self.min_radius,
+        }
+    }
+
+    #[inline]
+    fn layer_mut(&mut self, layer: MatrixLayer) -> &mut Vec<f64> {
+        match layer {
+            MatrixLayer::Force => &mut self.data,
+            MatrixLayer::MaxRadius => &mut self.max_radius,
+            MatrixLayer::MinRadius => &mut self.min_radius,
+        }
     }
 
-    fn copy_to_clipboard(&self) {
-        let output = self.data
-            .chunks_exact(self.color_count)
-            .map(|chunk| chunk
-                .into_iter()
-                .map(|f| f.to_string())
+    // csv holds the one layer, json the whole matrix with its type and radii
+    fn copy_to_clipboard(&self, layer: MatrixLayer, format: ClipboardFormat) -> Result<(), String> {
+        let output = match format {
+            ClipboardFormat::Csv => self.layer(layer)
+                .chunks_exact(self.color_count)
+                .map(|chunk| chunk
+                    .iter()
+                    .map(|f| f.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+                )
                 .collect::<Vec<_>>()
-                .join(",")
-            )
-            .collect::<Vec<_>>()
-            .join("\n");
-        let mut clipboard = Clipboard::new().unwrap();
-        clipboard.set_text(output).unwrap();
-    }
-
-    fn paste_from_clipboard(&mut self) {
-        let mut clipboard = Clipboard::new().unwrap();
-        if let Ok(contents) = clipboard.get_text() {
-            let mut data = Vec::with_capacity(self.color_count * self.color_count);
-            for line in contents.lines() {
-                let parts = line.split(',').collect::<Vec<_>>();
-                for part in parts {
-                    if let Ok(num) = part.trim().parse::<f64>() {
-                        data.push(num.into());
-                    } else {
-                        break;
-                    }
-                }
-            }
-            if data.len() == self.data.len() {
-                self.data = data;
+                .join("\n"),
+            ClipboardFormat::Json => serde_json::to_string_pretty(self).map_err(|err| err.to_string())?,
+        };
+        Clipboard::new()
+            .and_then(|mut clipboard| clipboard.set_text(output))
+            .map_err(|err| format!("clipboard unavailable: {err}"))
+    }
+
+    fn paste_from_clipboard<R: Rng + ?Sized>(
+        &mut self,
+        layer: MatrixLayer,
+        rng: &mut R,
+    ) -> Result<(ClipboardFormat, String), String> {
+        let contents = Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .map_err(|err| format!("clipboard unavailable: {err}"))?;
+        self.paste(layer, &contents, rng)
+    }
+
+    // replaces the whole matrix from json, or one layer from csv, resizing
+    // the other layers when the csv has a different color count; returns
+    // which of the two it was
+    fn paste<R: Rng + ?Sized>(
+        &mut self,
+        layer: MatrixLayer,
+        contents: &str,
+        rng: &mut R,
+    ) -> Result<(ClipboardFormat, String), String> {
+        if contents.trim_start().starts_with('{') {
+            let mut matrix: ForceMatrix = serde_json::from_str(contents)
+                .map_err(|err| format!("invalid matrix json: {err}"))?;
+            matrix.fill_radii();
+            if matrix.color_count > MAX_COLORS || !matrix.is_valid() {
+                return Err("matrix json is malformed or out of range".into());
             }
+            *self = matrix;
+            return Ok((
+                ClipboardFormat::Json,
+                format!("pasted a {} color {:?} matrix", self.color_count, self.matrix_type),
+            ));
+        }
+
+        let rows = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(y, line)| line
+                .split(',')
+                .map(|part| part.trim().parse::<f64>()
+                    .ok()
+                    .filter(|value| value.is_finite())
+                    .ok_or_else(|| format!("row {}: '{}' is not a number", y + 1, part.trim())))
+                .collect::<Result<Vec<_>, _>>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let count = rows.len();
+        if count == 0 {
+            return Err("the clipboard has no matrix in it".into());
         }
+        if count > MAX_COLORS {
+            return Err(format!("{count} colors is more than the {MAX_COLORS} supported"));
+        }
+        if let Some((y, row)) = rows.iter().enumerate().find(|(_, row)| row.len() != count) {
+            return Err(format!("row {} has {} values, a {count} color matrix needs {count}", y + 1, row.len()));
+        }
+        let data = rows.concat();
+        let out_of_range = data.iter().find(|r| !(MIN_RADIUS_SCALE..=MAX_RADIUS_SCALE).contains(*r));
+        if let Some(r) = out_of_range && layer != MatrixLayer::Force {
+            return Err(format!("radius {r} is outside {MIN_RADIUS_SCALE}..={MAX_RADIUS_SCALE}"));
+        }
+        while self.color_count < count {
+            self.expand(rng);
+        }
+        while self.color_count > count {
+            self.shrink();
+        }
+        *self.layer_mut(layer) = data;
+        Ok((ClipboardFormat::Csv, format!("pasted a {count} color matrix")))
     }
 
     #[inline]
@@ -124,6 +322,25 @@ impl ForceMatrix {
         }
     }
 
+    // how much the pair's interaction radius is scaled
+    #[inline]
+    pub fn get_max_radius(&self, x: usize, y: usize) -> f64 {
+        let ix = self.data_ix(x, y);
+        self.max_radius.get(ix).copied().unwrap_or(DEFAULT_RADIUS_SCALE)
+    }
+
+    // how much the pair's repulsion distance is scaled
+    #[inline]
+    pub fn get_min_radius(&self, x: usize, y: usize) -> f64 {
+        let ix = self.data_ix(x, y);
+        self.min_radius.get(ix).copied().unwrap_or(DEFAULT_RADIUS_SCALE)
+    }
+
+    // the largest interaction radius scale of any pair, which sizes the island grid
+    pub fn max_radius_scale(&self) -> f64 {
+        self.max_radius.iter().copied().fold(MIN_RADIUS_SCALE, f64::max)
+    }
+
     fn soften(&mut self) {
         for cell in &mut self.data {
             *cell *= 2.0 / 3.0;
@@ -142,127 +359,138 @@ impl ForceMatrix {
         }
     }
 
-    pub fn expand(&mut self) {
+    pub fn expand<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        // a whole fresh matrix keeps the new row and column consistent with
+        // each other for providers like the symmetric ones
         let new_size = self.color_count + 1;
+        let fresh = self.matrix_type.generate(new_size, rng);
         self.data = (0..new_size * new_size)
-            .into_iter()
             .map(|i| {
                 let x = i % new_size;
                 let y = i / new_size;
-                match self.get_data(x, y) {
-                    Some(cell) => cell.clone(),
-                    None => {
-                        let f = match self.matrix_type {
-                            ForceMatrixType::Chains(p) => p.force(x, y, new_size),
-                            ForceMatrixType::Random(p) => p.force(x, y, new_size),
-                            ForceMatrixType::Snakes(p) => p.force(x, y, new_size),
-                            ForceMatrixType::Zeros(p) => p.force(x, y, new_size),
-                            ForceMatrixType::Ones(p) => p.force(x, y, new_size),
-                        };
-                        f.into()
-                    },
+                match x < self.color_count && y < self.color_count {
+                    true => self.get_force(x, y),
+                    false => fresh[i],
                 }
             })
             .collect::<Vec<_>>();
+        self.max_radius = expand_square(&self.max_radius, self.color_count, DEFAULT_RADIUS_SCALE);
+        self.min_radius = expand_square(&self.min_radius, self.color_count, DEFAULT_RADIUS_SCALE);
         self.color_count = new_size;
     }
 
     pub fn shrink(&mut self) {
         if self.color_count > 1 {
-            let new_len = self.data.len() - self.color_count;
+            self.data = shrink_square(&self.data, self.color_count);
+            self.max_radius = shrink_square(&self.max_radius, self.color_count);
+            self.min_radius = shrink_square(&self.min_radius, self.color_count);
             self.color_count -= 1;
-            self.data = self.data[0..new_len]
-                .chunks_exact(self.color_count + 1)
-                .into_iter()
-                .flat_map(|chunks|
-                    chunks
-                        .iter()
-                        .take(self.color_count)
-                        .map(|cell| cell.clone())
-                )
-                .collect::<Vec<_>>();
         }
     }
 
-    fn shift_matrix(&mut self, shift_type: ForceShiftType, amount: isize) {
-        self.data = self.data
-            .iter()
-            .enumerate()
-            .map(|(i, _)| {
+    fn shift_matrix(&mut self, layer: MatrixLayer, shift_type: ForceShiftType, amount: isize) {
+        let count = self.color_count;
+        let data = self.layer(layer);
+        let shifted = (0..data.len())
+            .map(|i| {
                 let (x, y) = match shift_type {
                     ForceShiftType::Column => (
-                        (((i % self.color_count) as isize) + amount).rem_euclid(self.color_count as isize) as usize,
-                        i / self.color_count
+                        (((i % count) as isize) + amount).rem_euclid(count as isize) as usize,
+                        i / count
                     ),
                     ForceShiftType::Row => (
-                        i % self.color_count,
-                        (((i / self.color_count) as isize) + amount).rem_euclid(self.color_count as isize) as usize
+                        i % count,
+                        (((i / count) as isize) + amount).rem_euclid(count as isize) as usize
                     ),
                 };
-                self.get_force(x, y)
+                data[x + y * count]
             })
             .collect();
+        *self.layer_mut(layer) = shifted;
     }
 
-    pub fn force_matrix_ui(&mut self, ui: &mut Ui, config: &mut ConfigState) {
+    pub fn force_matrix_ui(
+        &mut self,
+        ui: &mut Ui,
+        config: &mut ConfigState,
+        palette: &Palette,
+        rng: &mut SimRng,
+        toasts: &mut Toasts,
+    ) {
+        let layer = config.matrix_layer;
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut config.matrix_layer, MatrixLayer::Force, "Force");
+            ui.selectable_value(&mut config.matrix_layer, MatrixLayer::MaxRadius, "Radius")
+                .on_hover_text("Scales the interaction radius per pair");
+            ui.selectable_value(&mut config.matrix_layer, MatrixLayer::MinRadius, "Repulsion")
+                .on_hover_text("Scales the repulsion distance per pair");
+        });
         ui.horizontal(|ui| {
             if ui.button(" < ").clicked() {
-                self.shift_matrix(ForceShiftType::Column, 1);
+                self.shift_matrix(layer, ForceShiftType::Column, 1);
             }
             if ui.button(" > ").clicked() {
-                self.shift_matrix(ForceShiftType::Column, -1);
+                self.shift_matrix(layer, ForceShiftType::Column, -1);
             }
             if ui.button(" ⬆ ").clicked() {
-                self.shift_matrix(ForceShiftType::Row, 1);
+                self.shift_matrix(layer, ForceShiftType::Row, 1);
             }
             if ui.button(" ⬇ ").clicked() {
-                self.shift_matrix(ForceShiftType::Row, -1);
+                self.shift_matrix(layer, ForceShiftType::Row, -1);
             }
         });
-        ui.horizontal(|ui| {
-            if ui.button(" Abs ").clicked() {
-                self.abs();
-            }
-            if ui.button(" Neg ").clicked() {
-                self.negate();
-            }
-            if ui.button(" Soft ").clicked() {
-                self.soften();
-            }
+        // signs only mean something for forces
+        ui.add_enabled_ui(layer == MatrixLayer::Force, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button(" Abs ").clicked() {
+                    self.abs();
+                }
+                if ui.button(" Neg ").clicked() {
+                    self.negate();
+                }
+                if ui.button(" Soft ").clicked() {
+                    self.soften();
+                }
+            });
         });
-        // todo: force matric color boxes
         egui::ScrollArea::both()
             .max_height(300.0)
             .show(ui, |ui| {
-                egui::Grid::new("force_matrix")
-                    .spacing([1.0, 1.0])
-                    .striped(true)
-                    .show(ui, |ui| {
-                        for y in 0..self.color_count {
-                            let y = y * self.color_count;
-                            for x in 0..self.color_count {
-                                if let Some(cell) = self.data.get_mut(x + y) {
-                                    ui.add(DragValue::new(cell).speed(0.001));
-                                }
-                            }
-                            ui.end_row();
-                        }
-                    });
+                let count = self.color_count;
+                matrix_grid(ui, self.layer_mut(layer), count, layer, palette);
             });
 
         ui.horizontal(|ui| {
-            if ui.button(" Copy ").clicked() {
-                self.copy_to_clipboard();
+            let mut copied = None;
+            if ui.button(" Copy ").on_hover_text("This layer as CSV").clicked() {
+                copied = Some(self.copy_to_clipboard(layer, ClipboardFormat::Csv));
+            }
+            if ui.button(" Copy JSON ").on_hover_text("Every layer, with the matrix type").clicked() {
+                copied = Some(self.copy_to_clipboard(layer, ClipboardFormat::Json));
             }
-            if ui.button(" Paste ").clicked() {
-                self.paste_from_clipboard();
+            if let Some(Err(err)) = copied {
+                toasts.error(format!("copy failed: {err}"));
+            }
+            if ui.button(" Paste ").on_hover_text("CSV into this layer, or JSON").clicked() {
+                match self.paste_from_clipboard(layer, rng) {
+                    Ok((format, msg)) => {
+                        // the palette and species follow the color count
+                        config.colors_count = self.color_count as u8;
+                        // and json brings its own matrix type
+                        if format == ClipboardFormat::Json {
+                            config.force_matrix_option = self.matrix_type();
+                        }
+                        toasts.info(msg);
+                    },
+                    Err(err) => toasts.error(format!("paste failed: {err}")),
+                }
             }
         });
 
         // forces select
         ui.horizontal(|ui| {
             if ui.button(" Update ").clicked() {
-                *self = ForceMatrix::new(config.colors_count as usize, config.force_matrix_option);
+                *self = ForceMatrix::new(config.colors_count as usize, config.force_matrix_option, rng);
             }
             egui::ComboBox::from_label("Matrix")
                 .selected_text(format!("{:?}", config.force_matrix_option))
@@ -271,26 +499,145 @@ impl ForceMatrix {
                     ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Truncate);
                     ui.set_min_width(60.0);
                     for f in ForceMatrixType::iter() {
-                        ui.selectable_value(&mut config.force_matrix_option, f, format!("{f}"));
+                        // keep the settings when picking the kind already selected
+                        let selected = config.force_matrix_option.same_kind(&f);
+                        if ui.selectable_label(selected, format!("{f}")).clicked() && !selected {
+                            config.force_matrix_option = f;
+                        }
                     }
                 });
             ui.end_row();
         });
+        config.force_matrix_option.params_ui(ui);
 
     }
 
 }
 
 
+// the side of one square in the matrix grid, in points
+const CELL_SIZE: f32 = 26.0;
+
+// a heatmap of one layer, the column color is pulled by the row color.
+// dragging a cell up or down changes it, right clicking resets it
+fn matrix_grid(ui: &mut Ui, data: &mut [f64], count: usize, layer: MatrixLayer, palette: &Palette) {
+    let (speed, reset) = match layer {
+        MatrixLayer::Force => (0.005, 0.0),
+        _ => (0.01, DEFAULT_RADIUS_SCALE),
+    };
+    let side = CELL_SIZE * (count + 1) as f32;
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(side, side), egui::Sense::hover());
+    let cell_rect = |x: usize, y: usize| egui::Rect::from_min_size(
+        rect.min + egui::vec2(x as f32, y as f32) * CELL_SIZE,
+        egui::Vec2::splat(CELL_SIZE),
+    ).shrink(0.5);
+    let painter = ui.painter_at(rect);
+    // headers
+    for i in 0..count {
+        painter.rect_filled(cell_rect(i + 1, 0).shrink(4.0), 3.0, palette.egui_color(i));
+        painter.rect_filled(cell_rect(0, i + 1).shrink(4.0), 3.0, palette.egui_color(i));
+    }
+    let id = ui.id().with("matrix_grid");
+    for y in 0..count {
+        for x in 0..count {
+            let Some(cell) = data.get_mut(x + y * count) else { continue };
+            let rect = cell_rect(x + 1, y + 1);
+            let response = ui.interact(rect, id.with((x, y)), egui::Sense::click_and_drag());
+            if response.dragged() {
+                *cell -= response.drag_delta().y as f64 * speed;
+            }
+            if response.secondary_clicked() {
+                *cell = reset;
+            }
+            if layer != MatrixLayer::Force {
+                *cell = cell.clamp(MIN_RADIUS_SCALE, MAX_RADIUS_SCALE);
+            }
+            let value = *cell;
+            painter.rect_filled(rect, 2.0, cell_color(value, layer));
+            if response.hovered() || response.dragged() {
+                painter.rect_stroke(rect, 2.0, (1.0, Color32::WHITE), egui::StrokeKind::Inside);
+            }
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                format!("{value:.1}"),
+                egui::FontId::proportional(9.0),
+                Color32::from_gray(220),
+            );
+            response.on_hover_text(format!("color {x} → color {y}: {value:.3}"));
+        }
+    }
+}
+
+// green for attraction and red for repulsion, or for radii above and below
+// the default, brighter the further from neutral
+fn cell_color(value: f64, layer: MatrixLayer) -> Color32 {
+    let strength = match layer {
+        MatrixLayer::Force => value,
+        _ if value >= DEFAULT_RADIUS_SCALE => (value - DEFAULT_RADIUS_SCALE) / (MAX_RADIUS_SCALE - DEFAULT_RADIUS_SCALE),
+        _ => (value - DEFAULT_RADIUS_SCALE) / (DEFAULT_RADIUS_SCALE - MIN_RADIUS_SCALE),
+    };
+    let shade = (strength.abs().min(1.0) * 200.0) as u8;
+    match strength >= 0.0 {
+        true => Color32::from_rgb(20, 20 + shade, 20),
+        false => Color32::from_rgb(20 + shade, 20, 20),
+    }
+}
+
+// the matrix one color bigger, with `fill` in the new row and column
+fn expand_square(data: &[f64], count: usize, fill: f64) -> Vec<f64> {
+    let new_size = count + 1;
+    (0..new_size * new_size)
+        .map(|i| {
+            let x = i % new_size;
+            let y = i / new_size;
+            match x < count && y < count {
+                true => data[x + y * count],
+                false => fill,
+            }
+        })
+        .collect()
+}
 
-trait MatrixProvider {
-    fn force(self, x: usize, y: usize, w: usize) -> f64;
+// the matrix without its last row and column
+fn shrink_square(data: &[f64], count: usize) -> Vec<f64> {
+    data[0..data.len() - count]
+        .chunks_exact(count)
+        .flat_map(|chunks| chunks.iter().take(count - 1).copied())
+        .collect()
 }
 
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+trait MatrixProvider: Copy {
+    fn force<R: Rng + ?Sized>(self, x: usize, y: usize, w: usize, rng: &mut R) -> f64;
+
+    // every cell in row order, providers that relate cells override this
+    fn generate<R: Rng + ?Sized>(self, w: usize, rng: &mut R) -> Vec<f64> {
+        (0..w * w)
+            .map(|i| self.force(i % w, i / w, w, rng))
+            .collect()
+    }
+}
+
+// fills the diagonal and upper triangle from `force`, mirroring it below
+// multiplied by `sign`
+fn mirrored<P: MatrixProvider, R: Rng + ?Sized>(provider: P, w: usize, sign: f64, rng: &mut R) -> Vec<f64> {
+    let mut data = vec![0.0; w * w];
+    for y in 0..w {
+        for x in y..w {
+            let force = provider.force(x, y, w, rng);
+            data[x + y * w] = force;
+            if x != y {
+                data[y + x * w] = sign * force;
+            }
+        }
+    }
+    data
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct ChainsForceMatrix;
 impl MatrixProvider for ChainsForceMatrix {
-    fn force(self, x: usize, y: usize, w: usize) -> f64 {
+    fn force<R: Rng + ?Sized>(self, x: usize, y: usize, w: usize, _: &mut R) -> f64 {
         let amt = 1.0;
         match (y, x) {
             (y, x) if y == x => amt,
@@ -301,18 +648,18 @@ impl MatrixProvider for ChainsForceMatrix {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct RandomForceMatrix;
 impl MatrixProvider for RandomForceMatrix {
-    fn force(self, _: usize, _: usize, _: usize) -> f64 {
-        random::<f64>() * 2.0 - 1.0
+    fn force<R: Rng + ?Sized>(self, _: usize, _: usize, _: usize, rng: &mut R) -> f64 {
+        rng.random::<f64>() * 2.0 - 1.0
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct SnakeForceMatrix;
 impl MatrixProvider for SnakeForceMatrix {
-    fn force(self, x: usize, y: usize, w: usize) -> f64 {
+    fn force<R: Rng + ?Sized>(self, x: usize, y: usize, w: usize, _: &mut R) -> f64 {
         match (y, x) {
             (y, x) if y == x => 1.0,
             (y, x) if y == (x + 1) % w => 0.2,
@@ -321,26 +668,203 @@ impl MatrixProvider for SnakeForceMatrix {
     }
 }
 
-// #[derive(Clone, Copy, Default, Debug, PartialEq)]
-// pub struct SymmetryForceMatrix(pub usize);
-// impl MatrixProvider for SymmetryForceMatrix {
-//     fn force(self, _x: usize, _y: usize, _w: usize) -> f64 {
-//         todo!()
-//     }
-// }
+// random, but every pair feels the same force towards each other
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct SymmetryForceMatrix {
+    pub amplitude: f64,
+}
+impl Default for SymmetryForceMatrix {
+    fn default() -> Self {
+        Self { amplitude: 1.0 }
+    }
+}
+impl MatrixProvider for SymmetryForceMatrix {
+    fn force<R: Rng + ?Sized>(self, _: usize, _: usize, _: usize, rng: &mut R) -> f64 {
+        (rng.random::<f64>() * 2.0 - 1.0) * self.amplitude
+    }
+
+    fn generate<R: Rng + ?Sized>(self, w: usize, rng: &mut R) -> Vec<f64> {
+        mirrored(self, w, 1.0, rng)
+    }
+}
+
+// predator and prey, whatever attracts one of a pair repels the other
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct AntisymmetryForceMatrix {
+    pub amplitude: f64,
+    pub diagonal: f64, // how much each color clumps with itself
+}
+impl Default for AntisymmetryForceMatrix {
+    fn default() -> Self {
+        Self { amplitude: 1.0, diagonal: 0.5 }
+    }
+}
+impl MatrixProvider for AntisymmetryForceMatrix {
+    fn force<R: Rng + ?Sized>(self, x: usize, y: usize, _: usize, rng: &mut R) -> f64 {
+        match x == y {
+            true => self.diagonal,
+            false => (rng.random::<f64>() * 2.0 - 1.0) * self.amplitude,
+        }
+    }
+
+    fn generate<R: Rng + ?Sized>(self, w: usize, rng: &mut R) -> Vec<f64> {
+        mirrored(self, w, -1.0, rng)
+    }
+}
+
+// every color treats the colors at the same offset from it the same way,
+// chains and snakes are both circulant
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct CirculantForceMatrix {
+    pub offsets: [f64; 5], // the force towards the colors 2 before through 2 after
+}
+impl Default for CirculantForceMatrix {
+    fn default() -> Self {
+        Self { offsets: [0.0, -0.5, 1.0, 0.5, 0.0] }
+    }
+}
+impl MatrixProvider for CirculantForceMatrix {
+    fn force<R: Rng + ?Sized>(self, x: usize, y: usize, w: usize, _: &mut R) -> f64 {
+        let half = self.offsets.len() as isize / 2;
+        // offsets wider than the matrix alias, the one nearest the centre wins
+        [0, 1, -1, 2, -2]
+            .into_iter()
+            .find(|d| (y as isize + d).rem_euclid(w as isize) == x as isize)
+            .map(|d| self.offsets[(half + d) as usize])
+            .unwrap_or(0.0)
+    }
+}
+
+const MAX_BLOCKS: usize = 16;
 
-#[derive(Clone, Copy, Default, Debug, PartialEq)]
+// colors split into consecutive communities that attract within and
+// push apart between
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct BlockForceMatrix {
+    pub blocks: usize,
+    pub within: f64,
+    pub between: f64,
+}
+impl Default for BlockForceMatrix {
+    fn default() -> Self {
+        Self { blocks: 2, within: 0.8, between: -0.3 }
+    }
+}
+impl MatrixProvider for BlockForceMatrix {
+    fn force<R: Rng + ?Sized>(self, x: usize, y: usize, w: usize, _: &mut R) -> f64 {
+        let blocks = self.blocks.max(1);
+        match x * blocks / w == y * blocks / w {
+            true => self.within,
+            false => self.between,
+        }
+    }
+}
+
+// random, but only `density` of the pairs interact at all
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct SparseForceMatrix {
+    pub density: f64,
+}
+impl Default for SparseForceMatrix {
+    fn default() -> Self {
+        Self { density: 0.3 }
+    }
+}
+impl MatrixProvider for SparseForceMatrix {
+    fn force<R: Rng + ?Sized>(self, _: usize, _: usize, _: usize, rng: &mut R) -> f64 {
+        // always draw both so the density doesn't change which values come up
+        let keep = rng.random::<f64>() < self.density;
+        let force = rng.random::<f64>() * 2.0 - 1.0;
+        match keep {
+            true => force,
+            false => 0.0,
+        }
+    }
+}
+
+// normally distributed forces, mostly weak with the odd strong one
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct GaussianForceMatrix {
+    pub mean: f64,
+    pub std_dev: f64,
+}
+impl Default for GaussianForceMatrix {
+    fn default() -> Self {
+        Self { mean: 0.0, std_dev: 0.5 }
+    }
+}
+impl MatrixProvider for GaussianForceMatrix {
+    fn force<R: Rng + ?Sized>(self, _: usize, _: usize, _: usize, rng: &mut R) -> f64 {
+        // box-muller, `1 - u` keeps the log away from zero
+        let u = 1.0 - rng.random::<f64>();
+        let v = rng.random::<f64>();
+        let normal = (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos();
+        self.mean + self.std_dev * normal
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug, Deserialize, PartialEq, Serialize)]
 pub struct ZeroForceMatrix;
 impl MatrixProvider for ZeroForceMatrix {
-    fn force(self, _: usize, _: usize, _: usize) -> f64 {
+    fn force<R: Rng + ?Sized>(self, _: usize, _: usize, _: usize, _: &mut R) -> f64 {
         0.0
     }
 }
 
-#[derive(Clone, Copy, Default, Debug, PartialEq)]
+#[derive(Clone, Copy, Default, Debug, Deserialize, PartialEq, Serialize)]
 pub struct IdentForceMatrix;
 impl MatrixProvider for IdentForceMatrix {
-    fn force(self, _: usize, _: usize, _: usize) -> f64 {
+    fn force<R: Rng + ?Sized>(self, _: usize, _: usize, _: usize, _: &mut R) -> f64 {
         1.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn paste_rejects_hostile_json() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut matrix = ForceMatrix::new(2, ForceMatrixType::Random(RandomForceMatrix), &mut rng);
+        let original = matrix.clone();
+        let mut json = serde_json::to_value(&matrix).unwrap();
+        for color_count in [0, MAX_COLORS as u64 + 1, 4_294_967_297, u64::MAX] {
+            json["color_count"] = color_count.into();
+            assert!(matrix.paste(MatrixLayer::Force, &json.to_string(), &mut rng).is_err());
+        }
+        json["color_count"] = 2.into();
+        json["data"] = serde_json::json!([1.0, 2.0, 3.0]);
+        assert!(matrix.paste(MatrixLayer::Force, &json.to_string(), &mut rng).is_err());
+        assert!(matrix == original);
+    }
+
+    fn circulant(w: usize) -> Vec<f64> {
+        CirculantForceMatrix::default().generate(w, &mut StdRng::seed_from_u64(0))
+    }
+
+    #[test]
+    fn circulant_prefers_nearer_offsets() {
+        assert_eq!(circulant(1), vec![1.0]);
+        assert_eq!(circulant(2), vec![1.0, 0.5, 0.5, 1.0]);
+        assert_eq!(circulant(3), vec![
+            1.0, 0.5, -0.5,
+            -0.5, 1.0, 0.5,
+            0.5, -0.5, 1.0,
+        ]);
+        let data = circulant(7);
+        for y in 0..7 {
+            for (d, force) in [(-2, 0.0), (-1, -0.5), (0, 1.0), (1, 0.5), (2, 0.0), (3, 0.0)] {
+                let x = (y as isize + d).rem_euclid(7) as usize;
+                assert_eq!(data[y * 7 + x], force);
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+use std::fmt::{Debug, Display, Formatter, Result};
+
+// how `ParticlePhysics::step` advances positions and velocities
+#[derive(Clone, Copy, Default, Deserialize, EnumIter, PartialEq, Serialize)]
+pub enum IntegratorType {
+    // one force evaluation per substep, kick then drift
+    #[default]
+    SemiImplicitEuler,
+    // half kick, drift, half kick, reusing the last evaluation between substeps
+    VelocityVerlet,
+    // four force evaluations per substep, with the drag inside the derivative
+    RungeKutta4,
+}
+
+impl Debug for IntegratorType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", match &self {
+            IntegratorType::SemiImplicitEuler => "Euler",
+            IntegratorType::VelocityVerlet => "Verlet",
+            IntegratorType::RungeKutta4 => "RK4",
+        })
+    }
+}
+
+impl Display for IntegratorType {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        Debug::fmt(&self, f)
+    }
+}
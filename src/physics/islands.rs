@@ -1,5 +1,5 @@
 use glam::DVec2;
-use super::bodies::PointBody;
+use super::boundary::BoundaryMode;
 
 const NEIGHBORS: [[isize; 2]; 9] = [
     [-1, -1], [0, -1], [1, -1],
@@ -7,33 +7,67 @@ const NEIGHBORS: [[isize; 2]; 9] = [
     [-1,  1], [0,  1], [1,  1],
 ];
 
+// the fewest buckets an open space hash starts with
+const MIN_BUCKETS: usize = 1 << 10;
+
 #[derive(Default)]
 pub struct IslandManager {
-    // islands: Vec<Island>,
-    islands: Vec<Vec<usize>>, // islands and their per step computed body indicies
+    island_starts: Vec<usize>, // where each island's bodies begin in the sorted arrays, plus the end
+    island_cursors: Vec<usize>, // scratch space for the sort
     neighbor_ixs: Vec<Vec<usize>>, // pre-cached neighbor indices
+    body_islands: Vec<usize>, // the island of each body
+    body_cells: Vec<[i64; 2]>, // the grid cell of each body, open space only
+    sorted_ixs: Vec<usize>, // body indices, grouped by island
+    sorted_positions: Vec<DVec2>, // body positions, grouped by island
+    sorted_colors: Vec<usize>, // body colors, grouped by island
+    boundary: BoundaryMode,
+    max_radius: f64,
     side_f64: f64,
     side: usize,
 }
 
+// Bounded modes split the unit square into a fixed grid of islands at least
+// `max_radius` wide, whose neighborhoods only wrap around the edges on a torus.
+// Open space has no square to split, so the same sized cells are hashed into
+// a table of islands that grows with the body count; cells that collide share
+// an island, which only costs the extra distance checks.
 impl IslandManager {
-    pub fn new(max_radius: f64) -> Self {
+    pub fn new(max_radius: f64, boundary: BoundaryMode) -> Self {
         let side = max_radius.recip().floor() as usize;
-        let size = side * side;
+        let size = match boundary.is_bounded() {
+            true => side * side,
+            false => MIN_BUCKETS,
+        };
         // build and return self
         let mut this = Self {
-            islands: vec![vec![]; size],
+            island_starts: vec![0; size + 1],
             neighbor_ixs: Vec::with_capacity(size),
+            boundary,
+            max_radius,
             side_f64: side as f64,
             side,
+            ..Default::default()
         };
-        this.setup_neighbors();
+        if boundary.is_bounded() {
+            this.setup_neighbors();
+        }
         this
     }
 
+    #[inline]
+    pub fn max_radius(&self) -> f64 {
+        self.max_radius
+    }
+
+    #[inline]
+    pub fn boundary(&self) -> BoundaryMode {
+        self.boundary
+    }
+
     // cache the computed indices of each island's group
     fn setup_neighbors(&mut self) {
         let side = self.side as isize;
+        let wraps = self.boundary.wraps();
         self.neighbor_ixs.clear();
         // for each island
         for i in 0..side * side {
@@ -44,52 +78,190 @@ impl IslandManager {
             for n in &NEIGHBORS {
                 let u = x + n[0];
                 let v = y + n[1];
+                // walls have nothing past them
+                let outside = !(0..side).contains(&u) || !(0..side).contains(&v);
+                if outside && !wraps {
+                    continue;
+                }
                 let j = u.rem_euclid(side) + v.rem_euclid(side) * side;
                 neighborhood.push(j as usize);
             }
             neighborhood.sort();
+            // a torus under 3 islands wide reaches the same island twice
+            neighborhood.dedup();
             self.neighbor_ixs.push(neighborhood);
         }
     }
 
-    // add each body's vec index into the appropriate island
-    pub fn index_positions(&mut self, bodies: &[&PointBody]) {
-        // clear all the islands w/o reallocating memory
-        for island in &mut self.islands {
-            island.clear();
+    // counting sort the bodies by island, copying their positions and colors
+    // so that walking an island is a walk over contiguous memory
+    pub fn index_positions(&mut self, positions: &[DVec2], colors: &[usize]) {
+        let count = positions.len();
+        if !self.boundary.is_bounded() {
+            // keep the table at least as big as the body count
+            let buckets = count.next_power_of_two().max(MIN_BUCKETS);
+            if buckets != self.island_starts.len() - 1 {
+                self.island_starts.resize(buckets + 1, 0);
+            }
         }
-        // for each body, add its index to the appropriate island
-        // based on its current position
-        for (bx, body) in bodies.iter().enumerate() {
-            let ix = self.get_local_island_ix(&body.position);
-            if let Some(island) = self.islands.get_mut(ix) {
-                island.push(bx);
+        // count the bodies in each island w/o reallocating memory
+        self.island_starts.fill(0);
+        self.body_islands.clear();
+        self.body_cells.clear();
+        for pos in positions {
+            let ix = match self.boundary.is_bounded() {
+                true => self.get_local_island_ix(pos),
+                false => {
+                    let cell = self.get_cell(pos);
+                    self.body_cells.push(cell);
+                    self.get_bucket_ix(cell)
+                },
+            };
+            self.body_islands.push(ix);
+            self.island_starts[ix + 1] += 1;
+        }
+        // turn the counts into offsets
+        for i in 1..self.island_starts.len() {
+            self.island_starts[i] += self.island_starts[i - 1];
+        }
+        // scatter each body into its island's slot, keeping index order within an island
+        self.sorted_ixs.resize(count, 0);
+        self.sorted_positions.resize(count, DVec2::ZERO);
+        self.sorted_colors.resize(count, 0);
+        self.island_cursors.clone_from(&self.island_starts);
+        for (bx, ix) in self.body_islands.iter().enumerate() {
+            let slot = self.island_cursors[*ix];
+            self.island_cursors[*ix] += 1;
+            self.sorted_ixs[slot] = bx;
+            self.sorted_positions[slot] = positions[bx];
+            self.sorted_colors[slot] = colors[bx];
+        }
+    }
+
+    // calls `f` with the index, position and color of every body in the
+    // islands surrounding body `bx`, including `bx` itself
+    #[inline]
+    pub fn for_each_neighbor(&self, bx: usize, mut f: impl FnMut(usize, DVec2, usize)) {
+        if self.boundary.is_bounded() {
+            for nix in &self.neighbor_ixs[self.body_islands[bx]] {
+                self.for_each_in_island(*nix, &mut f);
             }
+            return;
+        }
+        // neighboring cells may hash to the same bucket, visit each bucket once
+        let [x, y] = self.body_cells[bx];
+        let mut buckets = NEIGHBORS.map(|[u, v]| self.get_bucket_ix([x + u as i64, y + v as i64]));
+        buckets.sort_unstable();
+        for (i, nix) in buckets.iter().enumerate() {
+            if i > 0 && buckets[i - 1] == *nix { continue }
+            self.for_each_in_island(*nix, &mut f);
         }
     }
 
     #[inline]
-    pub fn get_neighboring_ixs(&self, pos: &DVec2) -> Vec<usize> {
-        let ix = self.get_local_island_ix(pos);
-        self.get_local_body_ixs(ix)
+    fn for_each_in_island(&self, ix: usize, f: &mut impl FnMut(usize, DVec2, usize)) {
+        for slot in self.island_starts[ix]..self.island_starts[ix + 1] {
+            f(self.sorted_ixs[slot], self.sorted_positions[slot], self.sorted_colors[slot]);
+        }
     }
 
     #[inline]
     fn get_local_island_ix(&self, pos: &DVec2) -> usize {
-        let x = (pos.x * self.side_f64) as usize;
-        let y = (pos.y * self.side_f64) as usize;
-        x + y * self.side
+        // a position exactly on the far edge is the near edge on a torus,
+        // and belongs to the last island between walls
+        let island = |v: f64| match self.boundary.wraps() {
+            true => (v * self.side_f64) as usize % self.side,
+            false => ((v * self.side_f64) as usize).min(self.side - 1),
+        };
+        island(pos.x) + island(pos.y) * self.side
     }
 
     #[inline]
-    fn get_local_body_ixs(&self, i: usize) -> Vec<usize> {
-        let mut ixs = Vec::new();
-        if let Some(nixs) = self.neighbor_ixs.get(i) {
-            for nix in nixs {
-                ixs.extend_from_slice(&self.islands[*nix]);
+    fn get_cell(&self, pos: &DVec2) -> [i64; 2] {
+        let cell = (*pos * self.side_f64).floor();
+        [cell.x as i64, cell.y as i64]
+    }
+
+    #[inline]
+    fn get_bucket_ix(&self, [x, y]: [i64; 2]) -> usize {
+        let mask = self.island_starts.len() - 2;
+        let hash = (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+        (hash ^ (hash >> 32)) as usize & mask
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    // random bodies plus ones sitting on cell edges and the square's edges
+    fn positions(open: bool) -> Vec<DVec2> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut edges = vec![0.0, 0.1, 0.0999999, 0.2, 0.3, 1.0 / 3.0, 0.5, 0.9, 0.9999999, 1.0];
+        if open {
+            edges.extend([-0.1, -0.0000001, -0.35, 1.0000001, 1.7, -2.5]);
+        }
+        let mut positions = edges
+            .iter()
+            .flat_map(|x| edges.iter().map(|y| DVec2::new(*x, *y)))
+            .collect::<Vec<_>>();
+        let (min, max) = match open {
+            true => (-1.0, 2.0),
+            false => (0.0, 1.0),
+        };
+        positions.extend((0..500).map(|_| DVec2::new(rng.random_range(min..max), rng.random_range(min..max))));
+        positions
+    }
+
+    // every body within the radius has to be visited, and nothing twice
+    fn assert_matches_brute_force(boundary: BoundaryMode, max_radius: f64) {
+        let positions = positions(!boundary.is_bounded());
+        let colors = (0..positions.len()).map(|ix| ix % 3).collect::<Vec<_>>();
+        let mut islands = IslandManager::new(max_radius, boundary);
+        islands.index_positions(&positions, &colors);
+        for (bx, pos) in positions.iter().enumerate() {
+            let mut visited = Vec::new();
+            islands.for_each_neighbor(bx, |ix, other, color| {
+                assert_eq!(other, positions[ix]);
+                assert_eq!(color, colors[ix]);
+                visited.push(ix);
+            });
+            visited.sort_unstable();
+            let count = visited.len();
+            visited.dedup();
+            assert_eq!(visited.len(), count, "{boundary:?}: body {bx} visited a body twice");
+            for (ix, other) in positions.iter().enumerate() {
+                if boundary.offset(*pos, *other).length() <= max_radius {
+                    assert!(
+                        visited.binary_search(&ix).is_ok(),
+                        "{boundary:?} at radius {max_radius}: {ix} {other} is missing from {bx} {pos}'s neighbors",
+                    );
+                }
             }
         }
-        ixs
     }
 
+    #[test]
+    fn wrap_neighbors_match_brute_force() {
+        // ten islands across, then a torus narrow enough to reach the same island twice
+        for max_radius in [0.1, 0.3, 0.45] {
+            assert_matches_brute_force(BoundaryMode::Wrap, max_radius);
+        }
+    }
+
+    #[test]
+    fn reflect_neighbors_match_brute_force() {
+        for max_radius in [0.1, 0.3, 0.45] {
+            assert_matches_brute_force(BoundaryMode::Reflect, max_radius);
+        }
+    }
+
+    #[test]
+    fn open_neighbors_match_brute_force() {
+        for max_radius in [0.1, 0.3] {
+            assert_matches_brute_force(BoundaryMode::Open, max_radius);
+        }
+    }
 }
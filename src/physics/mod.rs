@@ -1,5 +1,11 @@
 pub mod bodies;
+pub mod boundary;
 pub mod forces;
+pub mod integrators;
 mod islands;
+pub mod params;
+pub mod particles;
+#[allow(clippy::module_inception)]
 pub mod physics;
-pub mod plugin;
\ No newline at end of file
+pub mod plugin;
+pub mod species;
\ No newline at end of file
@@ -0,0 +1,138 @@
+use bevy::prelude::Resource;
+use bevy_egui::egui::{self, DragValue, Ui};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use super::{boundary::BoundaryMode, integrators::IntegratorType};
+use crate::providers::accelerators::AcceleratorType;
+
+// defaults
+const MAX_DIST: f64 = 0.02; // The maximum distance that a particle can interact with another
+const MIN_REL_DIST: f64 = 0.3; // The minimum relative distance that two particles can interact with
+const FRICTION_HALFLIFE: f64 = 0.043; // seconds for a particle's velocity to halve
+const FORCE_SCALE: f64 = 1.0;
+const TIME_STEP: f64 = 1.0 / 64.0; // the default `Time<Fixed>` timestep
+const SUBSTEPS: u32 = 1;
+
+// limits
+const MIN_MAX_DIST: f64 = 0.005; // keeps the island grid at a sane size
+const MAX_MAX_DIST: f64 = 0.25; // a 4x4 island grid at radius scale 1, down to 1x1 at the largest pair radii
+const MIN_MIN_REL_DIST: f64 = 0.01;
+const MAX_MIN_REL_DIST: f64 = 0.99;
+pub const MIN_FRICTION_HALFLIFE: f64 = 0.001;
+pub const MAX_FRICTION_HALFLIFE: f64 = 10.0;
+const MAX_FORCE_SCALE: f64 = 100.0;
+const MAX_SUBSTEPS: u32 = 32;
+pub const MIN_TIME_STEP: f64 = 0.0001;
+pub const MAX_TIME_STEP: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Resource, Serialize)]
+#[serde(default)]
+pub struct PhysicsParams {
+    pub accelerator: AcceleratorType,
+    pub boundary: BoundaryMode,
+    pub max_dist: f64,
+    pub min_rel_dist: f64,
+    pub friction_halflife: f64,
+    pub force_scale: f64,
+    pub time_step: f64,
+    pub integrator: IntegratorType,
+    pub substeps: u32,
+}
+
+impl Default for PhysicsParams {
+    fn default() -> Self {
+        Self {
+            accelerator: AcceleratorType::default(),
+            boundary: BoundaryMode::default(),
+            max_dist: MAX_DIST,
+            min_rel_dist: MIN_REL_DIST,
+            friction_halflife: FRICTION_HALFLIFE,
+            force_scale: FORCE_SCALE,
+            time_step: TIME_STEP,
+            integrator: IntegratorType::default(),
+            substeps: SUBSTEPS,
+        }
+    }
+}
+
+impl PhysicsParams {
+
+    // whether every value is in the range the ui allows, e.g. after reading a file
+    pub fn is_valid(&self) -> bool {
+        (MIN_MAX_DIST..=MAX_MAX_DIST).contains(&self.max_dist)
+            && (MIN_MIN_REL_DIST..=MAX_MIN_REL_DIST).contains(&self.min_rel_dist)
+            && (MIN_FRICTION_HALFLIFE..=MAX_FRICTION_HALFLIFE).contains(&self.friction_halflife)
+            && (0.0..=MAX_FORCE_SCALE).contains(&self.force_scale)
+            && (MIN_TIME_STEP..=MAX_TIME_STEP).contains(&self.time_step)
+            && (1..=MAX_SUBSTEPS).contains(&self.substeps)
+    }
+
+    pub fn params_ui(&mut self, ui: &mut Ui) {
+        egui::ComboBox::from_label("Kernel")
+            .selected_text(format!("{:?}", self.accelerator))
+            .show_ui(ui, |ui| {
+                ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Truncate);
+                ui.set_min_width(60.0);
+                for f in AcceleratorType::iter() {
+                    ui.selectable_value(&mut self.accelerator, f, format!("{f}"));
+                }
+            });
+        egui::ComboBox::from_label("Edges")
+            .selected_text(format!("{:?}", self.boundary))
+            .show_ui(ui, |ui| {
+                ui.set_min_width(60.0);
+                for b in BoundaryMode::iter() {
+                    ui.selectable_value(&mut self.boundary, b, format!("{b}"));
+                }
+            });
+        egui::ComboBox::from_label("Integrator")
+            .selected_text(format!("{:?}", self.integrator))
+            .show_ui(ui, |ui| {
+                ui.set_min_width(60.0);
+                for i in IntegratorType::iter() {
+                    ui.selectable_value(&mut self.integrator, i, format!("{i}"));
+                }
+            });
+        egui::Grid::new("physics_params")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Radius");
+                ui.add(DragValue::new(&mut self.max_dist)
+                    .speed(0.0005)
+                    .range(MIN_MAX_DIST..=MAX_MAX_DIST));
+                ui.end_row();
+                ui.label("Repulsion");
+                ui.add(DragValue::new(&mut self.min_rel_dist)
+                    .speed(0.005)
+                    .range(MIN_MIN_REL_DIST..=MAX_MIN_REL_DIST));
+                ui.end_row();
+                ui.label("Friction");
+                ui.add(DragValue::new(&mut self.friction_halflife)
+                    .speed(0.001)
+                    .range(MIN_FRICTION_HALFLIFE..=MAX_FRICTION_HALFLIFE)
+                    .suffix("s"));
+                ui.end_row();
+                ui.label("Force");
+                ui.add(DragValue::new(&mut self.force_scale)
+                    .speed(0.01)
+                    .range(0.0..=MAX_FORCE_SCALE));
+                ui.end_row();
+                ui.label("Time Step");
+                ui.add(DragValue::new(&mut self.time_step)
+                    .speed(0.0005)
+                    .range(MIN_TIME_STEP..=MAX_TIME_STEP)
+                    .suffix("s"));
+                ui.end_row();
+                ui.label("Substeps");
+                ui.add(DragValue::new(&mut self.substeps)
+                    .speed(0.05)
+                    .range(1..=MAX_SUBSTEPS));
+                ui.end_row();
+            });
+        if ui.button(" Reset ").clicked() {
+            *self = Self::default();
+        }
+    }
+
+}
@@ -0,0 +1,111 @@
+use glam::DVec2;
+use rayon::prelude::*;
+use super::{bodies::PointBody, boundary::BoundaryMode, species::SpeciesStep};
+
+// structure-of-arrays storage for every body in the simulation,
+// a body's index is stable until bodies are removed
+#[derive(Clone, Debug, Default)]
+pub struct Particles {
+    pub positions: Vec<DVec2>,
+    pub velocities: Vec<DVec2>,
+    pub colors: Vec<usize>,
+    generation: u64, // bumped by every removal, anything holding indices is stale once it moves
+}
+
+impl Particles {
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn clear(&mut self) {
+        self.generation += 1;
+        self.positions.clear();
+        self.velocities.clear();
+        self.colors.clear();
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.positions.reserve(additional);
+        self.velocities.reserve(additional);
+        self.colors.reserve(additional);
+    }
+
+    pub fn push(&mut self, body: PointBody) {
+        self.positions.push(body.position);
+        self.velocities.push(body.velocity);
+        self.colors.push(body.color);
+    }
+
+    // O(1) removal, the last body takes the removed body's index
+    pub fn swap_remove(&mut self, ix: usize) -> PointBody {
+        self.generation += 1;
+        PointBody {
+            color: self.colors.swap_remove(ix),
+            position: self.positions.swap_remove(ix),
+            velocity: self.velocities.swap_remove(ix),
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, ix: usize) -> PointBody {
+        PointBody {
+            color: self.colors[ix],
+            position: self.positions[ix],
+            velocity: self.velocities[ix],
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = PointBody> + '_ {
+        (0..self.len()).map(|ix| self.get(ix))
+    }
+
+    // degrade velocity before adding force, `species` has the coefficients
+    // for each color with the last standing in for unknown ones
+    pub fn kick(&mut self, forces: &[DVec2], dt: f64, species: &[SpeciesStep]) {
+        self.velocities
+            .par_iter_mut()
+            .zip(forces.par_iter())
+            .zip(self.colors.par_iter())
+            .for_each(|((velocity, force), color)| {
+                let species = &species[(*color).min(species.len() - 1)];
+                *velocity *= species.drag;
+                *velocity += force * species.inv_mass * dt;
+                *velocity = species.limit(*velocity);
+            });
+    }
+
+    // update position and apply the boundary
+    pub fn drift(&mut self, dt: f64, boundary: BoundaryMode) {
+        self.positions
+            .par_iter_mut()
+            .zip(self.velocities.par_iter_mut())
+            .for_each(|(position, velocity)| {
+                *position += *velocity * dt;
+                boundary.confine(position, velocity);
+            });
+    }
+
+}
+
+impl Extend<PointBody> for Particles {
+    fn extend<I: IntoIterator<Item = PointBody>>(&mut self, iter: I) {
+        for body in iter {
+            self.push(body);
+        }
+    }
+}
+
+impl FromIterator<PointBody> for Particles {
+    fn from_iter<I: IntoIterator<Item = PointBody>>(iter: I) -> Self {
+        let mut particles = Self::default();
+        particles.extend(iter);
+        particles
+    }
+}
@@ -1,73 +1,332 @@
 use bevy::prelude::Resource;
 use glam::DVec2;
 use rayon::prelude::*;
-use super::{islands::IslandManager, forces::ForceMatrix, bodies::PointBody};
+use super::{boundary::BoundaryMode, integrators::IntegratorType, islands::IslandManager, species::{SpeciesStep, SpeciesTable}, forces::ForceMatrix, params::PhysicsParams, particles::Particles};
+use crate::providers::accelerators::*;
 
-const MAX_DIST: f64 = 0.02; // The maximum distance that a particle can interact with another
-const MAX_DIST_RECIP: f64 = 1.0 / MAX_DIST;
-const MAX_DIST_SQRD: f64 = MAX_DIST * MAX_DIST;
-const MIN_REL_DIST: f64 = 0.3; // The minimum relative distance that two particles can interact with
-const MIN_DIST_RECIP: f64 = 1.0 / MIN_REL_DIST;
-const INV_MIN_DIST_RECIP: f64 = 1.0 / (1.0 - MIN_REL_DIST);
+// keeps the island grid at a sane size when every radius is scaled down
+const MIN_ISLAND_RADIUS: f64 = 0.005;
+// the repulsion zone has to leave some room for attraction
+const MAX_MIN_REL_DIST: f64 = 0.99;
 
 #[derive(Resource)]
 pub struct ParticlePhysics {
+    particles: Particles,
     forces: Vec<DVec2>,
     islands: IslandManager,
+    absorbed: Vec<usize>, // bodies that left through an absorbing edge last step
+    rk4: Rk4State,
+}
+
+// the start of the step and the weighted sum of the stage derivatives
+#[derive(Default)]
+struct Rk4State {
+    positions: Vec<DVec2>,
+    velocities: Vec<DVec2>,
+    dx: Vec<DVec2>,
+    dv: Vec<DVec2>,
 }
 
 impl Default for ParticlePhysics {
     fn default() -> Self {
         Self {
+            particles: Particles::default(),
             forces: Vec::new(),
-            islands: IslandManager::new(MAX_DIST),
+            islands: IslandManager::new(PhysicsParams::default().max_dist, BoundaryMode::default()),
+            absorbed: Vec::new(),
+            rk4: Rk4State::default(),
         }
     }
 }
 
 impl ParticlePhysics {
 
-    pub fn get_forces(&mut self, bodies: &[&PointBody], force_matrix: &ForceMatrix) -> &[DVec2] {
-        // bucket bodies, (broad phase?)
-        self.islands.index_positions(&bodies);
-        // aggregate forces
-        bodies
+    #[inline]
+    pub fn particles(&self) -> &Particles {
+        &self.particles
+    }
+
+    #[inline]
+    pub fn particles_mut(&mut self) -> &mut Particles {
+        &mut self.particles
+    }
+
+    // the net force on body `ix` from the last evaluation, while the body
+    // count hasn't changed since
+    pub fn force(&self, ix: usize) -> Option<DVec2> {
+        if self.forces.len() != self.particles.len() { return None }
+        self.forces.get(ix).copied()
+    }
+
+    // how many other bodies are within the island radius of body `ix`, the
+    // ones that could push or pull on it; a scan over the current positions
+    // since the islands were indexed before the last drift
+    pub fn neighbor_count(&self, ix: usize) -> Option<usize> {
+        if ix >= self.particles.len() { return None }
+        let pos0 = self.particles.positions[ix];
+        let radius_sqrd = self.islands.max_radius() * self.islands.max_radius();
+        let boundary = self.islands.boundary();
+        let count = self.particles.positions
             .par_iter()
-            .enumerate()
-            .map(|(ix, body0)| {
-                let mut total_force = DVec2::ZERO;
-                for jx in self.islands.get_neighboring_ixs(&body0.position) {
-                    if ix == jx { continue }
-                    total_force += get_force(body0, &bodies[jx], force_matrix);
+            .filter(|pos1| boundary.offset(pos0, **pos1).length_squared() <= radius_sqrd)
+            .count();
+        Some(count - 1)
+    }
+
+    // labels every body with the root of its connected component, bodies
+    // linking when they are within `link` of each other; `link` is capped at
+    // the island radius. the islands are re-indexed first, the last force
+    // evaluation saw the positions from before the drift
+    pub fn connected_components(&mut self, link: f64) -> Vec<usize> {
+        self.islands.index_positions(&self.particles.positions, &self.particles.colors);
+        let count = self.particles.len();
+        let link = link.min(self.islands.max_radius());
+        let link_sqrd = link * link;
+        let boundary = self.islands.boundary();
+        let positions = &self.particles.positions;
+        let mut roots = (0..count).collect::<Vec<_>>();
+        for ix in 0..count {
+            self.islands.for_each_neighbor(ix, |jx, _, _| {
+                // each pair once
+                if jx <= ix { return }
+                if boundary.offset(positions[ix], positions[jx]).length_squared() <= link_sqrd {
+                    let (a, b) = (find_root(&mut roots, ix), find_root(&mut roots, jx));
+                    roots[a.max(b)] = a.min(b);
+                }
+            });
+        }
+        for ix in 0..count {
+            roots[ix] = find_root(&mut roots, ix);
+        }
+        roots
+    }
+
+    // rebuild the grid if the largest interaction radius or the boundary changed
+    pub fn prepare(&mut self, force_matrix: &ForceMatrix, params: &PhysicsParams) {
+        let max_radius = (params.max_dist * force_matrix.max_radius_scale()).clamp(MIN_ISLAND_RADIUS, 1.0);
+        if self.islands.max_radius() != max_radius || self.islands.boundary() != params.boundary {
+            self.islands = IslandManager::new(max_radius, params.boundary);
+        }
+    }
+
+    // advance every particle by one time step, split into `substeps` pieces
+    pub fn step(&mut self, force_matrix: &ForceMatrix, species: &SpeciesTable, params: &PhysicsParams) {
+        let substeps = params.substeps.max(1);
+        let dt = params.time_step / substeps as f64;
+        let color_count = force_matrix.color_count();
+        match params.integrator {
+            IntegratorType::SemiImplicitEuler => {
+                let species = species.steps(color_count, dt, params);
+                for _ in 0..substeps {
+                    self.update_forces(force_matrix, params);
+                    self.particles.kick(&self.forces, dt, &species);
+                    self.particles.drift(dt, params.boundary);
+                }
+            },
+            IntegratorType::VelocityVerlet => {
+                // half the drag on each half kick
+                let species = species.steps(color_count, 0.5 * dt, params);
+                self.update_forces(force_matrix, params);
+                for _ in 0..substeps {
+                    self.particles.kick(&self.forces, 0.5 * dt, &species);
+                    self.particles.drift(dt, params.boundary);
+                    self.update_forces(force_matrix, params);
+                    self.particles.kick(&self.forces, 0.5 * dt, &species);
                 }
+            },
+            IntegratorType::RungeKutta4 => {
+                let species = species.steps(color_count, dt, params);
+                for _ in 0..substeps {
+                    self.step_rk4(force_matrix, &species, params, dt);
+                }
+            },
+        }
+        let boundary = params.boundary;
+        let positions = &self.particles.positions;
+        self.absorbed.clear();
+        self.absorbed.extend((0..positions.len()).filter(|ix| boundary.absorbs(positions[*ix])));
+    }
+
+    // classic RK4 on dx/dt = v, dv/dt = f(x) / m - k v, where the drag rate k
+    // gives the same half-life as the exponential drag of the other integrators
+    fn step_rk4(&mut self, force_matrix: &ForceMatrix, species: &[SpeciesStep], params: &PhysicsParams, dt: f64) {
+        let wraps = params.boundary.wraps();
+        let rk4 = &mut self.rk4;
+        rk4.positions.clone_from(&self.particles.positions);
+        rk4.velocities.clone_from(&self.particles.velocities);
+        rk4.dx.clear();
+        rk4.dx.resize(self.particles.len(), DVec2::ZERO);
+        rk4.dv.clear();
+        rk4.dv.resize(self.particles.len(), DVec2::ZERO);
+
+        // the weight of each stage's derivative, and how far along the next stage starts
+        for (weight, next) in [(1.0, 0.5), (2.0, 0.5), (2.0, 1.0), (1.0, 0.0)] {
+            // the particles hold the stage's state while its forces are evaluated
+            self.update_forces(force_matrix, params);
+            let rk4 = &mut self.rk4;
+            let particles = &mut self.particles;
+            particles.positions
+                .par_iter_mut()
+                .zip(particles.velocities.par_iter_mut())
+                .zip(self.forces.par_iter().zip(particles.colors.par_iter()))
+                .zip(rk4.dx.par_iter_mut().zip(rk4.dv.par_iter_mut()))
+                .zip(rk4.positions.par_iter().zip(rk4.velocities.par_iter()))
+                .for_each(|((((position, velocity), (force, color)), (dx, dv)), (position0, velocity0))| {
+                    let species = &species[(*color).min(species.len() - 1)];
+                    let stage_dx = species.limit(*velocity);
+                    let stage_dv = match species.frozen {
+                        true => DVec2::ZERO,
+                        false => force * species.inv_mass - species.drag_rate * *velocity,
+                    };
+                    *dx += weight * stage_dx;
+                    *dv += weight * stage_dv;
+                    *position = position0 + next * dt * stage_dx;
+                    *velocity = velocity0 + next * dt * stage_dv;
+                    // keep the stage inside the torus so it lands in the right island
+                    if wraps {
+                        *position = position.rem_euclid(DVec2::ONE);
+                    }
+                });
+        }
+
+        let particles = &mut self.particles;
+        let rk4 = &self.rk4;
+        particles.positions
+            .par_iter_mut()
+            .zip(particles.velocities.par_iter_mut())
+            .zip(rk4.dx.par_iter().zip(rk4.dv.par_iter()))
+            .zip(rk4.positions.par_iter().zip(rk4.velocities.par_iter()))
+            .zip(particles.colors.par_iter())
+            .for_each(|((((position, velocity), (dx, dv)), (position0, velocity0)), color)| {
+                let species = &species[(*color).min(species.len() - 1)];
+                *position = position0 + dt / 6.0 * dx;
+                *velocity = species.limit(velocity0 + dt / 6.0 * dv);
+            });
+        // apply the boundary without moving
+        particles.drift(0.0, params.boundary);
+    }
+
+    // put the bodies absorbed by the last step back at rest wherever `spawn` says
+    pub fn respawn_absorbed(&mut self, mut spawn: impl FnMut() -> DVec2) {
+        for ix in self.absorbed.drain(..) {
+            self.particles.positions[ix] = spawn();
+            self.particles.velocities[ix] = DVec2::ZERO;
+        }
+    }
+
+    pub fn update_forces(&mut self, force_matrix: &ForceMatrix, params: &PhysicsParams) -> &[DVec2] {
+        self.prepare(force_matrix, params);
+        // bucket bodies, (broad phase?)
+        self.islands.index_positions(&self.particles.positions, &self.particles.colors);
+        // pick the kernel once rather than per pair
+        match params.accelerator {
+            AcceleratorType::ParticleLife => self.aggregate::<ParticleLifeAccelerator>(force_matrix, params),
+            AcceleratorType::Smooth => self.aggregate::<SmoothAccelerator>(force_matrix, params),
+            AcceleratorType::Cubic => self.aggregate::<CubicAccelerator>(force_matrix, params),
+            AcceleratorType::LennardJones => self.aggregate::<LennardJonesAccelerator>(force_matrix, params),
+            AcceleratorType::InverseSquare => self.aggregate::<InverseSquareAccelerator>(force_matrix, params),
+            AcceleratorType::Rotator => self.aggregate::<RotatorAccelerator>(force_matrix, params),
+        }
+        &self.forces
+    }
+
+    fn aggregate<A: Accelerator>(&mut self, force_matrix: &ForceMatrix, params: &PhysicsParams) {
+        let kernel = ForceKernel::new(force_matrix, params);
+        let islands = &self.islands;
+        let particles = &self.particles;
+        (0..particles.len())
+            .into_par_iter()
+            .map(|ix| {
+                let pos0 = particles.positions[ix];
+                let color0 = particles.colors[ix];
+                let mut total_force = DVec2::ZERO;
+                islands.for_each_neighbor(ix, |jx, pos1, color1| {
+                    if ix == jx { return }
+                    total_force += kernel.get_force::<A>(pos0, color0, pos1, color1);
+                });
                 total_force
             })
             .collect_into_vec(&mut self.forces);
-        &self.forces
     }
 
 }
 
-fn get_force(body0: &PointBody, body1: &PointBody, forces: &ForceMatrix) -> DVec2 {
-    // shortest distance in wrapped toroidal space
-    let min_pos = (body1.position - body0.position + 0.5).rem_euclid(DVec2::ONE) - 0.5;
-    if min_pos.length_squared() > MAX_DIST_SQRD {
-        return DVec2::ZERO;
+// the physics params and force matrix with their derived values precomputed
+// for every color pair
+struct ForceKernel {
+    pairs: Vec<PairKernel>,
+    unknown_pair: PairKernel, // for colors the matrix hasn't caught up with
+    color_count: usize,
+    force_scale: f64,
+    boundary: BoundaryMode,
+}
+
+struct PairKernel {
+    attraction: f64,
+    max_dist: f64,
+    max_dist_recip: f64,
+    max_dist_sqrd: f64,
+    min_rel_dist: f64,
+}
+
+impl PairKernel {
+    fn new(attraction: f64, max_radius: f64, min_radius: f64, params: &PhysicsParams) -> Self {
+        let max_dist = params.max_dist * max_radius;
+        Self {
+            attraction,
+            max_dist,
+            max_dist_recip: 1.0 / max_dist,
+            max_dist_sqrd: max_dist * max_dist,
+            min_rel_dist: (params.min_rel_dist * min_radius).min(MAX_MIN_REL_DIST),
+        }
     }
+}
 
-    let pos = min_pos * MAX_DIST_RECIP;
-    let dist = pos.length();
+impl ForceKernel {
+    fn new(force_matrix: &ForceMatrix, params: &PhysicsParams) -> Self {
+        let color_count = force_matrix.color_count();
+        let pairs = (0..color_count * color_count)
+            .map(|i| {
+                let (x, y) = (i / color_count, i % color_count);
+                PairKernel::new(
+                    force_matrix.get_force(x, y),
+                    force_matrix.get_max_radius(x, y),
+                    force_matrix.get_min_radius(x, y),
+                    params,
+                )
+            })
+            .collect();
+        Self {
+            pairs,
+            unknown_pair: PairKernel::new(0.0, 1.0, 1.0, params),
+            color_count,
+            force_scale: params.force_scale,
+            boundary: params.boundary,
+        }
+    }
 
-    let force;
-    if dist <= MIN_REL_DIST {
-        force = dist * MIN_DIST_RECIP - 1.0;
-    } else {
-        let f = forces.get_force(body0.color, body1.color);
-        if f == 0.0 {
+    #[inline]
+    fn get_force<A: Accelerator>(&self, pos0: DVec2, color0: usize, pos1: DVec2, color1: usize) -> DVec2 {
+        let pair = self.pairs.get(color0 * self.color_count + color1).unwrap_or(&self.unknown_pair);
+        // shortest distance, through the edges when wrapping
+        let min_pos = self.boundary.offset(pos0, pos1);
+        if min_pos.length_squared() > pair.max_dist_sqrd {
             return DVec2::ZERO;
         }
-        force = f * (1.0 - (1.0 + MIN_REL_DIST - 2.0 * dist) * INV_MIN_DIST_RECIP);
-    };
 
-    force / dist * MAX_DIST * pos
+        let pos = min_pos * pair.max_dist_recip;
+        let dist = pos.length();
+
+        self.force_scale * pair.max_dist * A::accelerate(pair.attraction, pos, dist, pair.min_rel_dist)
+    }
+}
+
+// union-find lookup, halving the path on the way up
+#[inline]
+fn find_root(roots: &mut [usize], mut ix: usize) -> usize {
+    while roots[ix] != ix {
+        roots[ix] = roots[roots[ix]];
+        ix = roots[ix];
+    }
+    ix
 }
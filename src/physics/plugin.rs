@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-
+use super::{forces::ForceMatrix, params::PhysicsParams, physics::ParticlePhysics, species::SpeciesTable};
 
 #[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum PhysicsSystems {
@@ -9,3 +9,52 @@ pub enum PhysicsSystems {
     Writeback,
 }
 
+// Steps every particle once per `FixedUpdate`, advancing the simulation by
+// `PhysicsParams::time_step`; the fixed timestep follows it so simulated time
+// keeps pace with the wall clock. The app provides the `ForceMatrix`; until it does, nothing runs. Systems that
+// mirror particles into the world (e.g. `Transform`s) belong in `Writeback`.
+pub struct ParticlePhysicsPlugin;
+
+impl Plugin for ParticlePhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ParticlePhysics>()
+            .init_resource::<PhysicsParams>()
+            .init_resource::<SpeciesTable>()
+            .configure_sets(FixedUpdate, (
+                PhysicsSystems::Prepare,
+                PhysicsSystems::StepSimulation,
+                PhysicsSystems::Writeback,
+            ).chain())
+            .add_systems(PreUpdate, sync_timestep.run_if(resource_changed::<PhysicsParams>))
+            .add_systems(FixedUpdate, (
+                prepare_physics
+                    .in_set(PhysicsSystems::Prepare)
+                    .run_if(resource_exists::<ForceMatrix>),
+                step_bodies
+                    .in_set(PhysicsSystems::StepSimulation)
+                    .run_if(resource_exists::<ForceMatrix>),
+            ));
+    }
+}
+
+fn sync_timestep(mut time: ResMut<Time<Fixed>>, params: Res<PhysicsParams>) {
+    time.set_timestep_seconds(params.time_step);
+}
+
+fn prepare_physics(
+    mut physics: ResMut<ParticlePhysics>,
+    force_matrix: Res<ForceMatrix>,
+    params: Res<PhysicsParams>,
+) {
+    physics.prepare(&force_matrix, &params);
+}
+
+fn step_bodies(
+    mut physics: ResMut<ParticlePhysics>,
+    force_matrix: Res<ForceMatrix>,
+    params: Res<PhysicsParams>,
+    species: Res<SpeciesTable>,
+) {
+    physics.step(&force_matrix, &species, &params);
+}
@@ -0,0 +1,176 @@
+use bevy::prelude::Resource;
+use bevy_egui::egui::{self, DragValue, Ui};
+use glam::DVec2;
+use serde::{Deserialize, Serialize};
+
+use super::params::{MAX_FRICTION_HALFLIFE, MIN_FRICTION_HALFLIFE, PhysicsParams};
+use crate::palette::Palette;
+
+// defaults
+const MASS: f64 = 1.0;
+const MAX_SPEED: f64 = 1.0; // unit squares per second, once a cap is turned on
+
+// limits
+const MIN_MASS: f64 = 0.01;
+const MAX_MASS: f64 = 100.0;
+const MIN_MAX_SPEED: f64 = 0.001;
+const MAX_MAX_SPEED: f64 = 100.0;
+
+// the properties shared by every particle of one color
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Species {
+    pub mass: f64,
+    // overrides the global friction when set
+    pub friction_halflife: Option<f64>,
+    pub max_speed: Option<f64>,
+    // anchored in place, still pushing and pulling everything else
+    pub frozen: bool,
+}
+
+impl Default for Species {
+    fn default() -> Self {
+        Self {
+            mass: MASS,
+            friction_halflife: None,
+            max_speed: None,
+            frozen: false,
+        }
+    }
+}
+
+impl Species {
+    pub fn is_valid(&self) -> bool {
+        (MIN_MASS..=MAX_MASS).contains(&self.mass)
+            && self.friction_halflife.is_none_or(|h| (MIN_FRICTION_HALFLIFE..=MAX_FRICTION_HALFLIFE).contains(&h))
+            && self.max_speed.is_none_or(|s| (MIN_MAX_SPEED..=MAX_MAX_SPEED).contains(&s))
+    }
+
+    fn halflife(&self, params: &PhysicsParams) -> f64 {
+        self.friction_halflife.unwrap_or(params.friction_halflife)
+    }
+}
+
+// how one species' particles move over a step of a given length
+#[derive(Clone, Copy, Debug)]
+pub struct SpeciesStep {
+    pub drag: f64, // velocity multiplier over the step
+    pub drag_rate: f64, // the same drag as a continuous rate, for RK4
+    pub inv_mass: f64,
+    pub max_speed: f64,
+    pub frozen: bool,
+}
+
+impl SpeciesStep {
+    fn new(species: &Species, dt: f64, params: &PhysicsParams) -> Self {
+        let halflife = species.halflife(params);
+        Self {
+            drag: 0.5f64.powf(dt / halflife),
+            drag_rate: std::f64::consts::LN_2 / halflife,
+            inv_mass: 1.0 / species.mass,
+            max_speed: species.max_speed.unwrap_or(f64::INFINITY),
+            frozen: species.frozen,
+        }
+    }
+
+    // frozen particles don't move, everything else is held to the speed cap
+    #[inline]
+    pub fn limit(&self, velocity: DVec2) -> DVec2 {
+        match self.frozen {
+            true => DVec2::ZERO,
+            false => velocity.clamp_length_max(self.max_speed),
+        }
+    }
+}
+
+// indexed by color, colors past the end behave like the default species
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Resource, Serialize)]
+#[serde(transparent)]
+pub struct SpeciesTable(Vec<Species>);
+
+impl SpeciesTable {
+
+    #[inline]
+    pub fn get(&self, color: usize) -> Species {
+        self.0.get(color).copied().unwrap_or_default()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.0.iter().all(Species::is_valid)
+    }
+
+    // keep one species per color, new colors get the defaults
+    pub fn resize(&mut self, colors: usize) {
+        self.0.resize(colors, Species::default());
+    }
+
+    // like `resize` but never drops species, so a color that comes back keeps its settings
+    pub fn grow(&mut self, colors: usize) {
+        if self.0.len() < colors {
+            self.resize(colors);
+        }
+    }
+
+    // the per-color step coefficients, with one extra entry for unknown colors
+    pub fn steps(&self, color_count: usize, dt: f64, params: &PhysicsParams) -> Vec<SpeciesStep> {
+        (0..=color_count)
+            .map(|color| SpeciesStep::new(&self.get(color), dt, params))
+            .collect()
+    }
+
+    pub fn species_ui(&mut self, ui: &mut Ui, palette: &Palette, params: &PhysicsParams) {
+        egui::Grid::new("species")
+            .num_columns(5)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.label("Mass").on_hover_text("Divides the force on the particle");
+                ui.label("Friction").on_hover_text("Overrides the global friction half-life");
+                ui.label("Max Speed");
+                ui.label("Frozen");
+                ui.end_row();
+                for (color, species) in self.0.iter_mut().take(palette.len()).enumerate() {
+                    let (rect, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                    ui.painter().rect_filled(rect, 2.0, palette.egui_color(color));
+                    ui.add(DragValue::new(&mut species.mass)
+                        .speed(0.01)
+                        .range(MIN_MASS..=MAX_MASS));
+                    optional_value(ui, &mut species.friction_halflife, params.friction_halflife, |value| {
+                        DragValue::new(value)
+                            .speed(0.001)
+                            .range(MIN_FRICTION_HALFLIFE..=MAX_FRICTION_HALFLIFE)
+                            .suffix("s")
+                    });
+                    optional_value(ui, &mut species.max_speed, MAX_SPEED, |value| {
+                        DragValue::new(value)
+                            .speed(0.01)
+                            .range(MIN_MAX_SPEED..=MAX_MAX_SPEED)
+                    });
+                    ui.checkbox(&mut species.frozen, "");
+                    ui.end_row();
+                }
+            });
+        if ui.button(" Reset ").clicked() {
+            self.0.fill(Species::default());
+        }
+    }
+
+}
+
+// a checkbox that turns the value on, starting from `default`
+fn optional_value(
+    ui: &mut Ui,
+    value: &mut Option<f64>,
+    default: f64,
+    drag: impl for<'a> FnOnce(&'a mut f64) -> DragValue<'a>,
+) {
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        if ui.checkbox(&mut enabled, "").changed() {
+            *value = enabled.then_some(default);
+        }
+        if let Some(value) = value {
+            ui.add(drag(value));
+        }
+    });
+}
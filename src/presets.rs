@@ -0,0 +1,331 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Ui};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::{
+    config::{ConfigState, MAX_BODIES},
+    palette::Palette,
+    physics::{forces::{ForceMatrix, ForceMatrixType}, params::PhysicsParams, species::SpeciesTable},
+    rng::SimRng,
+    snapshot::SavedConfig,
+    toasts::Toasts,
+};
+
+const PRESETS_DIR: &str = "presets";
+const PRESET_EXT: &str = "json";
+// built-in matrices are generated from a fixed seed so they look the same every time
+const BUILTIN_SEED: u64 = 1;
+
+// a named simulation setup, without any particle positions
+#[derive(Deserialize, Serialize)]
+pub struct Preset {
+    config: SavedConfig,
+    forces: ForceMatrix,
+    #[serde(default)]
+    physics: PhysicsParams,
+    #[serde(default)]
+    species: SpeciesTable,
+    // srgb colors, the default hues when empty
+    #[serde(default)]
+    palette: Vec<[u8; 3]>,
+}
+
+impl Preset {
+    pub fn new(
+        config: &ConfigState,
+        forces: &ForceMatrix,
+        physics: &PhysicsParams,
+        species: &SpeciesTable,
+        palette: &Palette,
+    ) -> Self {
+        let mut species = species.clone();
+        species.resize(config.colors_count as usize);
+        Self {
+            config: config.into(),
+            forces: forces.clone(),
+            physics: *physics,
+            species,
+            palette: palette.to_rgb(),
+        }
+    }
+
+    // the default setup with one of the matrix types
+    fn builtin(matrix_type: ForceMatrixType) -> Self {
+        let config = ConfigState {
+            force_matrix_option: matrix_type,
+            ..default()
+        };
+        let mut rng = SimRng::new(BUILTIN_SEED);
+        let forces = ForceMatrix::new(config.colors_count as usize, matrix_type, &mut rng);
+        Self {
+            config: (&config).into(),
+            forces,
+            physics: PhysicsParams::default(),
+            species: SpeciesTable::default(),
+            palette: Vec::new(),
+        }
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = io::BufWriter::new(fs::File::create(path)?);
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = io::BufReader::new(fs::File::open(path)?);
+        let mut preset: Self = serde_json::from_reader(file)?;
+        preset.forces.fill_radii();
+        preset.validate()?;
+        Ok(preset)
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        if self.forces.color_count() != self.config.colors_count as usize {
+            return Err(invalid("force matrix does not match the color count".into()));
+        }
+        if !self.forces.is_valid() {
+            return Err(invalid("force matrix data is malformed".into()));
+        }
+        if !self.species.is_valid() {
+            return Err(invalid("species table is out of range".into()));
+        }
+        if !self.physics.is_valid() {
+            return Err(invalid("physics parameters are out of range".into()));
+        }
+        if self.config.bodies_count > MAX_BODIES {
+            return Err(invalid(format!("too many bodies ({})", self.config.bodies_count)));
+        }
+        if !self.palette.is_empty() && self.palette.len() != self.config.colors_count as usize {
+            return Err(invalid("palette does not match the color count".into()));
+        }
+        Ok(())
+    }
+
+    // puts the setup in use and respawns the particles; the seed is left alone
+    pub fn apply(
+        self,
+        config: &mut ConfigState,
+        force_matrix: &mut ForceMatrix,
+        params: &mut PhysicsParams,
+        species: &mut SpeciesTable,
+        palette: &mut Palette,
+    ) {
+        config.bodies_count = self.config.bodies_count;
+        config.colors_count = self.config.colors_count;
+        config.force_matrix_option = self.config.force_matrix_option;
+        config.position_option = self.config.position_option;
+        config.reset_bodies = true;
+        *force_matrix = self.forces;
+        *params = self.physics;
+        *species = self.species;
+        *palette = match self.palette.is_empty() {
+            true => Palette::new(config.colors_count as usize),
+            false => Palette::from_rgb(&self.palette),
+        };
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+enum PresetSource {
+    Builtin(ForceMatrixType),
+    File(PathBuf),
+}
+
+// the preset files on disk and what the browser has asked for
+#[derive(Resource)]
+pub struct PresetLibrary {
+    dir: PathBuf,
+    names: Option<Vec<String>>, // scanned the first time the browser is shown
+    selected: Option<String>,
+    name: String,
+    load_requested: Option<PresetSource>,
+    save_requested: Option<String>,
+}
+
+impl Default for PresetLibrary {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from(PRESETS_DIR),
+            names: None,
+            selected: None,
+            name: String::new(),
+            load_requested: None,
+            save_requested: None,
+        }
+    }
+}
+
+impl PresetLibrary {
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.{PRESET_EXT}"))
+    }
+
+    // a preset file, or failing that the name of one in the presets directory
+    pub fn find(&self, name: &str) -> PathBuf {
+        match Path::new(name).is_file() {
+            true => PathBuf::from(name),
+            false => self.path(name),
+        }
+    }
+
+    pub fn request_load(&mut self, path: PathBuf) {
+        self.load_requested = Some(PresetSource::File(path));
+    }
+
+    fn refresh(&mut self) {
+        let mut names = fs::read_dir(&self.dir)
+            .map(|entries| entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == PRESET_EXT))
+                .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+                .collect::<Vec<_>>())
+            .unwrap_or_default();
+        names.sort();
+        if self.selected.as_ref().is_some_and(|name| !names.contains(name)) {
+            self.selected = None;
+        }
+        self.names = Some(names);
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> io::Result<()> {
+        let target = self.path(to);
+        if target.exists() {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("'{to}' already exists")));
+        }
+        fs::rename(self.path(from), target)
+    }
+
+    pub fn presets_ui(&mut self, ui: &mut Ui, toasts: &mut Toasts) {
+        if self.names.is_none() {
+            self.refresh();
+        }
+        ui.label("Built-in:");
+        ui.horizontal_wrapped(|ui| {
+            for matrix_type in ForceMatrixType::iter() {
+                if ui.small_button(format!("{matrix_type}")).clicked() {
+                    self.load_requested = Some(PresetSource::Builtin(matrix_type));
+                }
+            }
+        });
+
+        ui.label(format!("Saved in {}/:", self.dir.display()));
+        let mut clicked = None;
+        egui::ScrollArea::vertical()
+            .id_salt("presets")
+            .max_height(120.0)
+            .show(ui, |ui| {
+                for name in self.names.iter().flatten() {
+                    let selected = self.selected.as_ref() == Some(name);
+                    let response = ui.selectable_label(selected, name);
+                    if response.clicked() {
+                        clicked = Some((name.clone(), false));
+                    }
+                    if response.double_clicked() {
+                        clicked = Some((name.clone(), true));
+                    }
+                }
+            });
+        if let Some((name, load)) = clicked {
+            self.name.clone_from(&name);
+            if load {
+                self.request_load(self.path(&name));
+            }
+            self.selected = Some(name);
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.name)
+                .hint_text("name")
+                .desired_width(120.0));
+            let name = self.name.trim().to_string();
+            if ui.add_enabled(valid_name(&name), egui::Button::new(" Save ")).clicked() {
+                self.save_requested = Some(name);
+            }
+        });
+        ui.horizontal(|ui| {
+            let Some(selected) = self.selected.clone() else { return };
+            if ui.button(" Load ").clicked() {
+                self.request_load(self.path(&selected));
+            }
+            let name = self.name.trim().to_string();
+            let can_rename = valid_name(&name) && name != selected;
+            if ui.add_enabled(can_rename, egui::Button::new(" Rename ")).on_hover_text("To the name above").clicked() {
+                match self.rename(&selected, &name) {
+                    Ok(()) => self.selected = Some(name),
+                    Err(err) => toasts.error(format!("rename failed: {err}")),
+                }
+                self.refresh();
+            }
+            if ui.button(" Delete ").clicked() {
+                if let Err(err) = fs::remove_file(self.path(&selected)) {
+                    toasts.error(format!("delete failed: {err}"));
+                }
+                self.refresh();
+            }
+        });
+        if ui.small_button(" Refresh ").clicked() {
+            self.refresh();
+        }
+    }
+
+}
+
+// a plain file name, nothing that reaches outside the presets directory
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\', ':'])
+}
+
+pub fn save_preset(
+    mut library: ResMut<PresetLibrary>,
+    mut toasts: ResMut<Toasts>,
+    config: Res<ConfigState>,
+    force_matrix: Res<ForceMatrix>,
+    palette: Res<Palette>,
+    params: Res<PhysicsParams>,
+    species: Res<SpeciesTable>,
+) {
+    let Some(name) = library.save_requested.take() else { return };
+    let preset = Preset::new(&config, &force_matrix, &params, &species, &palette);
+    let result = fs::create_dir_all(&library.dir).and_then(|_| preset.write(library.path(&name)));
+    match result {
+        Ok(()) => toasts.info(format!("saved preset '{name}'")),
+        Err(err) => toasts.error(format!("save failed: {err}")),
+    }
+    library.selected = Some(name);
+    library.refresh();
+}
+
+pub fn load_preset(
+    mut config: ResMut<ConfigState>,
+    mut force_matrix: ResMut<ForceMatrix>,
+    mut library: ResMut<PresetLibrary>,
+    mut palette: ResMut<Palette>,
+    mut params: ResMut<PhysicsParams>,
+    mut species: ResMut<SpeciesTable>,
+    mut toasts: ResMut<Toasts>,
+) {
+    let Some(source) = library.load_requested.take() else { return };
+    let (name, preset) = match source {
+        PresetSource::Builtin(matrix_type) => (format!("{matrix_type}"), Preset::builtin(matrix_type)),
+        PresetSource::File(path) => match Preset::read(&path) {
+            Ok(preset) => (path.file_stem().unwrap_or_default().to_string_lossy().into_owned(), preset),
+            Err(err) => {
+                toasts.error(format!("load failed: {err}"));
+                return;
+            },
+        },
+    };
+    preset.apply(&mut config, &mut force_matrix, &mut params, &mut species, &mut palette);
+    toasts.info(format!("loaded preset '{name}'"));
+}
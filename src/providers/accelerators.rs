@@ -0,0 +1,140 @@
+use glam::DVec2;
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+use std::{f64::consts::PI, fmt::{Debug, Display, Formatter, Result}};
+
+#[derive(Clone, Copy, Default, Deserialize, EnumIter, PartialEq, Serialize)]
+pub enum AcceleratorType {
+    #[default]
+    ParticleLife,
+    Smooth,
+    Cubic,
+    LennardJones,
+    InverseSquare,
+    Rotator,
+}
+
+impl Debug for AcceleratorType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", match &self {
+            AcceleratorType::ParticleLife => "Particle Life",
+            AcceleratorType::Smooth => "Smooth",
+            AcceleratorType::Cubic => "Cubic",
+            AcceleratorType::LennardJones => "Lennard-Jones",
+            AcceleratorType::InverseSquare => "Inv Square",
+            AcceleratorType::Rotator => "Rotator",
+        })
+    }
+}
+
+impl Display for AcceleratorType {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+// `a` is the attraction from the force matrix, `pos` the offset to the other
+// body and `dist` its length, both relative to the interaction radius, and
+// `min_dist` the relative radius of the repulsion zone
+pub trait Accelerator {
+    fn accelerate(a: f64, pos: DVec2, dist: f64, min_dist: f64) -> DVec2;
+}
+
+// linear push apart inside the repulsion zone, -1 at zero distance
+#[inline]
+fn repulsion(dist: f64, min_dist: f64) -> f64 {
+    dist / min_dist - 1.0
+}
+
+// how far through the attraction zone the distance is, from 0 to 1
+#[inline]
+fn attraction_t(dist: f64, min_dist: f64) -> f64 {
+    (dist - min_dist) / (1.0 - min_dist)
+}
+
+#[inline]
+fn radial(force: f64, pos: DVec2, dist: f64) -> DVec2 {
+    force / dist * pos
+}
+
+pub struct ParticleLifeAccelerator;
+impl Accelerator for ParticleLifeAccelerator {
+    fn accelerate(a: f64, pos: DVec2, dist: f64, min_dist: f64) -> DVec2 {
+        let force = if dist <= min_dist {
+            repulsion(dist, min_dist)
+        } else if a == 0.0 {
+            return DVec2::ZERO;
+        } else {
+            a * (1.0 - (1.0 + min_dist - 2.0 * dist) / (1.0 - min_dist))
+        };
+        radial(force, pos, dist)
+    }
+}
+
+pub struct SmoothAccelerator;
+impl Accelerator for SmoothAccelerator {
+    fn accelerate(a: f64, pos: DVec2, dist: f64, min_dist: f64) -> DVec2 {
+        let force = if dist <= min_dist {
+            repulsion(dist, min_dist)
+        } else {
+            // quartic bump, zero with zero slope at both ends
+            let u = 2.0 * attraction_t(dist, min_dist) - 1.0;
+            let s = 1.0 - u * u;
+            a * s * s
+        };
+        radial(force, pos, dist)
+    }
+}
+
+pub struct CubicAccelerator;
+impl Accelerator for CubicAccelerator {
+    fn accelerate(a: f64, pos: DVec2, dist: f64, min_dist: f64) -> DVec2 {
+        let force = if dist <= min_dist {
+            // eases into the repulsion rather than starting at full strength
+            let r = 1.0 - dist / min_dist;
+            -r * r * r
+        } else {
+            // peaks a third of the way through the attraction zone
+            let t = attraction_t(dist, min_dist);
+            a * 6.75 * t * (1.0 - t) * (1.0 - t)
+        };
+        radial(force, pos, dist)
+    }
+}
+
+pub struct LennardJonesAccelerator;
+impl Accelerator for LennardJonesAccelerator {
+    fn accelerate(a: f64, pos: DVec2, dist: f64, min_dist: f64) -> DVec2 {
+        // 12-6 potential with the matrix scaling the attractive term, tapered
+        // to zero at the radius and clamped so overlaps don't explode
+        let s6 = (min_dist / dist).powi(6);
+        let force = ((a * s6 - s6 * s6) * (1.0 - dist)).max(-1.0);
+        radial(force, pos, dist)
+    }
+}
+
+pub struct InverseSquareAccelerator;
+impl Accelerator for InverseSquareAccelerator {
+    fn accelerate(a: f64, pos: DVec2, dist: f64, min_dist: f64) -> DVec2 {
+        let force = if dist <= min_dist {
+            repulsion(dist, min_dist)
+        } else {
+            // cut off at the interaction radius
+            let s = min_dist / dist;
+            a * s * s
+        };
+        radial(force, pos, dist)
+    }
+}
+
+pub struct RotatorAccelerator;
+impl Accelerator for RotatorAccelerator {
+    fn accelerate(a: f64, pos: DVec2, dist: f64, min_dist: f64) -> DVec2 {
+        if dist <= min_dist {
+            return radial(repulsion(dist, min_dist), pos, dist);
+        }
+        // push sideways rather than towards, so pairs orbit each other
+        let force = a * (PI * attraction_t(dist, min_dist)).sin();
+        radial(force, pos.perp(), dist)
+    }
+}
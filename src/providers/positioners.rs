@@ -1,23 +1,24 @@
 use glam::DVec2;
-use rand::{Rng as _, rng};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use strum::EnumIter;
 use std::{f64::consts::TAU, fmt::{Debug, Display, Formatter, Result}};
 
-pub fn get_position(pos_type: &PositionerType) -> DVec2 {
+pub fn get_position<R: Rng + ?Sized>(pos_type: &PositionerType, rng: &mut R) -> DVec2 {
     0.5 + 0.5 * match pos_type {
-        PositionerType::BigBang => BigBangPositioner::get_pos(),
-        PositionerType::Circle => CirclePositioner::get_pos(),
-        PositionerType::Line => LinePositioner::get_pos(),
-        PositionerType::SRing => SRingPositioner::get_pos(),
-        PositionerType::MRing => MRingPositioner::get_pos(),
-        PositionerType::LRing => LRingPositioner::get_pos(),
-        PositionerType::Spiral => SpiralPositioner::get_pos(),
-        PositionerType::Uniform => UniformPositioner::get_pos(),
-        PositionerType::UniformCircle => UniformCirclePositioner::get_pos(),
+        PositionerType::BigBang => BigBangPositioner::get_pos(rng),
+        PositionerType::Circle => CirclePositioner::get_pos(rng),
+        PositionerType::Line => LinePositioner::get_pos(rng),
+        PositionerType::SRing => SRingPositioner::get_pos(rng),
+        PositionerType::MRing => MRingPositioner::get_pos(rng),
+        PositionerType::LRing => LRingPositioner::get_pos(rng),
+        PositionerType::Spiral => SpiralPositioner::get_pos(rng),
+        PositionerType::Uniform => UniformPositioner::get_pos(rng),
+        PositionerType::UniformCircle => UniformCirclePositioner::get_pos(rng),
     }
 }
 
-#[derive(Clone, Copy, EnumIter, PartialEq)]
+#[derive(Clone, Copy, Deserialize, EnumIter, PartialEq, Serialize)]
 pub enum PositionerType {
     BigBang,
     Circle,
@@ -53,13 +54,12 @@ impl Display for PositionerType {
 }
 
 pub trait Positioner {
-    fn get_pos() -> DVec2;
+    fn get_pos<R: Rng + ?Sized>(rng: &mut R) -> DVec2;
 }
 
 pub struct BigBangPositioner;
 impl Positioner for BigBangPositioner {
-    fn get_pos() -> DVec2 {
-        let mut rng = rng();
+    fn get_pos<R: Rng + ?Sized>(rng: &mut R) -> DVec2 {
         let radius = rng.random::<f64>() * 0.01;
         let theta = rng.random::<f64>() * TAU;
         DVec2 {
@@ -71,8 +71,7 @@ impl Positioner for BigBangPositioner {
 
 pub struct CirclePositioner;
 impl Positioner for CirclePositioner {
-    fn get_pos() -> DVec2 {
-        let mut rng = rng();
+    fn get_pos<R: Rng + ?Sized>(rng: &mut R) -> DVec2 {
         let radius = rng.random::<f64>();
         let theta = rng.random::<f64>() * TAU;
         DVec2 {
@@ -91,8 +90,7 @@ impl Positioner for CirclePositioner {
 
 pub struct LinePositioner;
 impl Positioner for LinePositioner {
-    fn get_pos() -> DVec2 {
-        let mut rng = rng();
+    fn get_pos<R: Rng + ?Sized>(rng: &mut R) -> DVec2 {
         DVec2 {
             x: rng.random::<f64>() * 2.0 - 1.0,
             y: (rng.random::<f64>() - 0.5) * 0.25
@@ -102,8 +100,7 @@ impl Positioner for LinePositioner {
 
 pub struct SRingPositioner;
 impl Positioner for SRingPositioner {
-    fn get_pos() -> DVec2 {
-        let mut rng = rng();
+    fn get_pos<R: Rng + ?Sized>(rng: &mut R) -> DVec2 {
         let radius = rng.random::<f64>() * 0.1 + 0.125;
         let theta = rng.random::<f64>() * TAU;
         DVec2 {
@@ -115,8 +112,7 @@ impl Positioner for SRingPositioner {
 
 pub struct MRingPositioner;
 impl Positioner for MRingPositioner {
-    fn get_pos() -> DVec2 {
-        let mut rng = rng();
+    fn get_pos<R: Rng + ?Sized>(rng: &mut R) -> DVec2 {
         let radius = rng.random::<f64>() * 0.1 + 0.25;
         let theta = rng.random::<f64>() * TAU;
         DVec2 {
@@ -128,8 +124,7 @@ impl Positioner for MRingPositioner {
 
 pub struct LRingPositioner;
 impl Positioner for LRingPositioner {
-    fn get_pos() -> DVec2 {
-        let mut rng = rng();
+    fn get_pos<R: Rng + ?Sized>(rng: &mut R) -> DVec2 {
         let radius = rng.random::<f64>() * 0.1 + 1.0 / 3.0;
         let theta = rng.random::<f64>() * TAU;
         DVec2 {
@@ -141,9 +136,7 @@ impl Positioner for LRingPositioner {
 
 pub struct SpiralPositioner;
 impl Positioner for SpiralPositioner {
-    fn get_pos() -> DVec2 {
-        // let mut rng = Random::default();
-        let mut rng = rng();
+    fn get_pos<R: Rng + ?Sized>(rng: &mut R) -> DVec2 {
         let max_rotations = 2.0;
         let f = rng.random::<f64>();
         let angle = max_rotations * TAU * f;
@@ -158,8 +151,7 @@ impl Positioner for SpiralPositioner {
 
 pub struct UniformPositioner;
 impl Positioner for UniformPositioner {
-    fn get_pos() -> DVec2 {
-        let mut rng = rng();
+    fn get_pos<R: Rng + ?Sized>(rng: &mut R) -> DVec2 {
         -1.0 + 2.0 * DVec2 {
             x: rng.random::<f64>(),
             y: rng.random::<f64>()
@@ -169,8 +161,7 @@ impl Positioner for UniformPositioner {
 
 pub struct UniformCirclePositioner;
 impl Positioner for UniformCirclePositioner {
-    fn get_pos() -> DVec2 {
-        let mut rng = rng();
+    fn get_pos<R: Rng + ?Sized>(rng: &mut R) -> DVec2 {
         let radius = rng.random::<f64>().sqrt();
         let theta = rng.random::<f64>() * TAU;
         DVec2 {
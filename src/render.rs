@@ -0,0 +1,391 @@
+use bevy::{
+    asset::{RenderAssetUsages, embedded_asset},
+    camera::visibility::NoFrustumCulling,
+    mesh::{Indices, MeshVertexAttribute, MeshVertexBufferLayoutRef},
+    prelude::*,
+    render::{
+        render_resource::{
+            AsBindGroup, PrimitiveTopology, RenderPipelineDescriptor, ShaderType,
+            SpecializedMeshPipelineError, VertexFormat,
+        },
+        storage::ShaderStorageBuffer,
+    },
+    shader::ShaderRef,
+    sprite_render::{Material2d, Material2dKey, Material2dPlugin},
+};
+use glam::DVec2;
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+use std::fmt::{self, Debug, Display, Formatter};
+
+use crate::{
+    config::ConfigState,
+    palette::Palette,
+    physics::{boundary::BoundaryMode, params::PhysicsParams, particles::Particles, physics::ParticlePhysics},
+};
+
+const RADIUS: f32 = 0.5833334; // 0.5 * (0.5 + 2.0 / 3.0);
+const SHADER_PATH: &str = "embedded://particlelife_bevy/shaders/particles.wgsl";
+// the particle itself plus a white ghost one tile over on each side
+const GHOST_COPIES: u32 = 5;
+// keeps a zoomed out infinite view from multiplying the particle count too far
+const MAX_TILES_ACROSS: i32 = 7;
+// the most quads drawn, copies and all; past it the copies are dropped
+const MAX_QUADS: usize = 1 << 23;
+
+// which quad a vertex belongs to and which of its corners it is, as
+// `quad * 4 + corner`; the draw's base vertex makes the builtin vertex index
+// unreliable for this once the mesh shares a buffer
+const ATTRIBUTE_QUAD: MeshVertexAttribute =
+    MeshVertexAttribute::new("Particle_Quad", 829_470_113, VertexFormat::Uint32);
+
+#[derive(Clone, Copy, Default, Deserialize, EnumIter, PartialEq, Serialize)]
+pub enum TilingMode {
+    Single,
+    #[default]
+    Ghosts,
+    Grid,
+    Infinite,
+}
+
+impl Debug for TilingMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match &self {
+            TilingMode::Single => "Single",
+            TilingMode::Ghosts => "Ghosts",
+            TilingMode::Grid => "3x3",
+            TilingMode::Infinite => "Infinite",
+        })
+    }
+}
+
+impl Display for TilingMode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+// how many copies of the torus to draw and where the first one sits
+struct Tiling {
+    copies: u32,
+    tiles_x: u32,
+    tile_min: IVec2,
+    ghosts: bool,
+}
+
+impl Tiling {
+    fn grid(min: IVec2, max: IVec2) -> Self {
+        let size = max - min + 1;
+        Self {
+            copies: (size.x * size.y) as u32,
+            tiles_x: size.x as u32,
+            tile_min: min,
+            ghosts: false,
+        }
+    }
+}
+
+// Draws every particle in one draw call. The mesh is just a run of quads that
+// grows in powers of two, the quads past the live count collapse in the vertex
+// shader; each frame the particles' positions and palette indices are copied
+// straight from `ParticlePhysics` into a storage buffer that the vertex shader
+// reads by quad index.
+pub struct ParticleRenderPlugin;
+
+impl Plugin for ParticleRenderPlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "shaders/particles.wgsl");
+        app
+            .add_plugins(Material2dPlugin::<ParticleMaterial>::default())
+            .add_systems(Startup, setup_particle_mesh)
+            .add_systems(PostUpdate, (update_particle_palette, update_particle_mesh));
+    }
+}
+
+#[derive(Clone, Copy, ShaderType)]
+struct ParticleSettings {
+    ghost_color: LinearRgba,
+    center: Vec2,
+    half_side: f32,
+    radius: f32,
+    quads: u32, // how many of the mesh's quads are drawn
+    copies: u32,
+    tiles_x: u32,
+    tile_min: IVec2,
+    ghosts: u32,
+    wrap: u32,
+}
+
+#[derive(Asset, AsBindGroup, Clone, TypePath)]
+struct ParticleMaterial {
+    #[uniform(0)]
+    settings: ParticleSettings,
+    #[storage(1, read_only)]
+    particles: Handle<ShaderStorageBuffer>,
+    #[storage(2, read_only)]
+    palette: Handle<ShaderStorageBuffer>,
+}
+
+impl Material2d for ParticleMaterial {
+    fn vertex_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // everything else comes from the storage buffers
+        let vertex_layout = layout.0.get_layout(&[
+            ATTRIBUTE_QUAD.at_shader_location(0),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+struct ParticleRender {
+    entity: Entity,
+    material: Handle<ParticleMaterial>,
+    mesh: Handle<Mesh>,
+    capacity: usize, // quads in the mesh
+    view_center: DVec2,
+    // reused every frame to stage the particle buffer
+    staging: Vec<Vec4>,
+}
+
+fn setup_particle_mesh(
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ParticleMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let mesh = meshes.add(Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default()));
+    let material = materials.add(ParticleMaterial {
+        settings: ParticleSettings {
+            ghost_color: LinearRgba::WHITE,
+            center: Vec2::splat(0.5),
+            half_side: 0.0,
+            radius: RADIUS,
+            quads: 0,
+            copies: GHOST_COPIES,
+            tiles_x: 1,
+            tile_min: IVec2::ZERO,
+            ghosts: 1,
+            wrap: 1,
+        },
+        // storage bindings can't be empty
+        particles: buffers.add(ShaderStorageBuffer::from(vec![Vec4::ZERO])),
+        palette: buffers.add(ShaderStorageBuffer::from(vec![Vec4::ONE])),
+    });
+    let entity = commands
+        .spawn((
+            Mesh2d(mesh.clone()),
+            MeshMaterial2d(material.clone()),
+            Transform::default(),
+            Visibility::Hidden,
+            // the particles can be anywhere, whatever the mesh's bounds say
+            NoFrustumCulling,
+        ))
+        .id();
+    commands.insert_resource(ParticleRender {
+        entity,
+        material,
+        mesh,
+        capacity: 0,
+        view_center: DVec2::splat(0.5),
+        staging: Vec::new(),
+    });
+}
+
+fn update_particle_palette(
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    mut materials: ResMut<Assets<ParticleMaterial>>,
+    palette: Res<Palette>,
+    render: Res<ParticleRender>,
+) {
+    if !palette.is_changed() { return }
+    let Some(material) = materials.get_mut(&render.material) else { return };
+    material.settings.ghost_color = palette.white().into();
+    if let Some(buffer) = buffers.get_mut(&material.palette) {
+        let colors = (0..palette.len())
+            .map(|i| Vec4::from_array(LinearRgba::from(palette.get(i)).to_f32_array()))
+            .collect::<Vec<_>>();
+        buffer.set_data(colors);
+    }
+}
+
+fn update_particle_mesh(
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    mut camera: Query<(&mut Transform, &Projection), With<Camera2d>>,
+    mut materials: ResMut<Assets<ParticleMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut render: ResMut<ParticleRender>,
+    mut visibility: Query<&mut Visibility>,
+    config: Res<ConfigState>,
+    params: Res<PhysicsParams>,
+    physics: Res<ParticlePhysics>,
+) {
+    let particles = physics.particles();
+    let wraps = params.boundary.wraps();
+    if let Ok(mut visibility) = visibility.get_mut(render.entity) {
+        visibility.set_if_neq(match particles.len() {
+            0 => Visibility::Hidden,
+            _ => Visibility::Inherited,
+        });
+    }
+    if particles.len() == 0 { return }
+
+    // bring the newly centered spot back under the camera
+    if config.view_center != render.view_center {
+        render.view_center = config.view_center;
+        if let Ok((mut transform, _)) = camera.single_mut() {
+            transform.translation.x = 0.0;
+            transform.translation.y = 0.0;
+        }
+    }
+
+    // only a torus repeats, and only while the copies fit in the budget
+    let single = Tiling::grid(IVec2::ZERO, IVec2::ZERO);
+    let budget = MAX_QUADS / particles.len();
+    let tiling = match config.tiling {
+        _ if !wraps => single,
+        TilingMode::Single => single,
+        TilingMode::Ghosts if budget < GHOST_COPIES as usize => single,
+        TilingMode::Ghosts => Tiling { copies: GHOST_COPIES, tiles_x: 1, tile_min: IVec2::ZERO, ghosts: true },
+        TilingMode::Grid if budget < 9 => single,
+        TilingMode::Grid => Tiling::grid(IVec2::NEG_ONE, IVec2::ONE),
+        TilingMode::Infinite => match camera.single() {
+            Ok((transform, Projection::Orthographic(ortho))) => {
+                visible_tiles(ortho.area, transform.translation.truncate(), config.half_side, budget)
+            },
+            _ if budget < 9 => single,
+            _ => Tiling::grid(IVec2::NEG_ONE, IVec2::ONE),
+        },
+    };
+
+    // grown by doubling and shrunk once mostly unused, so spawning, erasing
+    // and panning over tiles don't rebuild it every frame
+    let quads = particles.len() * tiling.copies as usize;
+    if quads > render.capacity || quads < render.capacity / 4 {
+        render.capacity = quads.next_power_of_two();
+        if let Some(mesh) = meshes.get_mut(&render.mesh) {
+            let capacity = render.capacity as u32;
+            let vertices = (0..capacity * 4).collect::<Vec<_>>();
+            let indices = (0..capacity)
+                .flat_map(|q| {
+                    let v = q * 4;
+                    [v, v + 1, v + 2, v, v + 2, v + 3]
+                })
+                .collect::<Vec<_>>();
+            mesh.insert_attribute(ATTRIBUTE_QUAD, vertices);
+            mesh.insert_indices(Indices::U32(indices));
+        }
+    }
+
+    let render = &mut *render;
+    render.staging.clear();
+    render.staging.extend(particles.positions
+        .iter()
+        .zip(&particles.colors)
+        .map(|(position, color)| Vec4::new(position.x as f32, position.y as f32, *color as f32, 0.0)));
+
+    // touching the material makes it pick up the rewritten buffer
+    let Some(material) = materials.get_mut(&render.material) else { return };
+    let settings = &mut material.settings;
+    settings.center = config.view_center.as_vec2();
+    settings.half_side = config.half_side;
+    settings.quads = quads as u32;
+    settings.copies = tiling.copies;
+    settings.tiles_x = tiling.tiles_x;
+    settings.tile_min = tiling.tile_min;
+    settings.ghosts = tiling.ghosts as u32;
+    settings.wrap = wraps as u32;
+    if let Some(buffer) = buffers.get_mut(&material.particles) {
+        buffer.set_data(&render.staging);
+    }
+}
+
+// the tiles overlapping the camera's view, centered on the one under it and
+// never more than `budget` of them
+fn visible_tiles(area: Rect, camera: Vec2, half_side: f32, budget: usize) -> Tiling {
+    let side = half_side * 2.0;
+    if side <= 0.0 {
+        return Tiling::grid(IVec2::ZERO, IVec2::ZERO);
+    }
+    // tile i spans [(2i - 1), (2i + 1)] * half_side
+    let tile = |x: f32| ((x + half_side) / side).floor() as i32;
+    let middle = IVec2::new(tile(camera.x), tile(camera.y));
+    // the grid is 2 * reach + 1 wide, an odd width that has to fit in `across`
+    let across = ((budget as f64).sqrt() as i32).min(MAX_TILES_ACROSS);
+    let reach = (across.max(1) - 1) / 2;
+    let min = IVec2::new(tile(camera.x + area.min.x), tile(camera.y + area.min.y)).max(middle - reach);
+    let max = IVec2::new(tile(camera.x + area.max.x), tile(camera.y + area.max.y)).min(middle + reach);
+    Tiling::grid(min, max)
+}
+
+// the simulation position drawn at `world`, undoing the shader's placement;
+// on a torus every tile maps back into the unit square
+pub fn sim_position(world: Vec2, half_side: f32, view_center: DVec2, boundary: BoundaryMode) -> DVec2 {
+    let pos = world.as_dvec2() / (2.0 * half_side as f64) + view_center;
+    match boundary.wraps() {
+        true => pos.rem_euclid(DVec2::ONE),
+        false => pos,
+    }
+}
+
+// where the shader draws `pos` in the tile under the view center
+pub fn world_position(pos: DVec2, half_side: f32, view_center: DVec2, boundary: BoundaryMode) -> Vec2 {
+    let shifted = pos - view_center + 0.5;
+    let shifted = match boundary.wraps() {
+        true => shifted.rem_euclid(DVec2::ONE),
+        false => shifted,
+    };
+    ((shifted - 0.5) * 2.0 * half_side as f64).as_vec2()
+}
+
+// the centroid of the particles around `ix`, found by averaging their
+// wrapped offsets so a cluster straddling the seam doesn't average out to
+// the middle of the square
+pub fn cluster_center(particles: &Particles, ix: usize, radius: f64, boundary: BoundaryMode) -> DVec2 {
+    let origin = particles.positions[ix];
+    let radius_sqrd = radius * radius;
+    let (sum, count) = particles.positions
+        .iter()
+        .map(|pos| boundary.offset(origin, *pos))
+        .filter(|offset| offset.length_squared() <= radius_sqrd)
+        .fold((DVec2::ZERO, 0), |(sum, count), offset| (sum + offset, count + 1));
+    let center = origin + sum / count as f64;
+    match boundary.wraps() {
+        true => center.rem_euclid(DVec2::ONE),
+        false => center,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MAX_BODIES;
+
+    #[test]
+    fn visible_tiles_stay_within_budget() {
+        // zoomed far out over a unit half side, so the view covers every tile in reach
+        let area = Rect::new(-1000.0, -1000.0, 1000.0, 1000.0);
+        for budget in 1..100 {
+            let tiling = visible_tiles(area, Vec2::ZERO, 1.0, budget);
+            assert!(tiling.copies as usize <= budget, "{} copies for a budget of {budget}", tiling.copies);
+        }
+        // the largest body counts leave room for only a few copies
+        for bodies in [1_000_000, MAX_BODIES as usize] {
+            let budget = MAX_QUADS / bodies;
+            assert_eq!(visible_tiles(area, Vec2::ZERO, 1.0, budget).copies, 1);
+        }
+        assert_eq!(visible_tiles(area, Vec2::ZERO, 1.0, usize::MAX).copies, (MAX_TILES_ACROSS * MAX_TILES_ACROSS) as u32);
+    }
+}
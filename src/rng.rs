@@ -0,0 +1,34 @@
+use bevy::prelude::Resource;
+use rand::{RngCore, SeedableRng, rngs::StdRng};
+
+// the single source of randomness for the simulation, so that the same
+// seed and config always produce the same initial state
+#[derive(Resource)]
+pub struct SimRng(StdRng);
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.0 = StdRng::seed_from_u64(seed);
+    }
+}
+
+impl RngCore for SimRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        self.0.fill_bytes(dst)
+    }
+}
@@ -0,0 +1,97 @@
+// Draws every particle as a disc, pulling its position and palette index
+// from the particle buffer by quad index rather than from vertex data.
+
+#import bevy_sprite::mesh2d_functions::{get_world_from_local, mesh2d_position_local_to_clip}
+
+struct ParticleSettings {
+    ghost_color: vec4<f32>,
+    // the spot in the unit square drawn at the middle of the tile
+    center: vec2<f32>,
+    half_side: f32,
+    radius: f32,
+    // the quads past this are spare capacity and collapse to nothing
+    quads: u32,
+    copies: u32,
+    // copies are laid out row by row, `tiles_x` wide, from `tile_min`
+    tiles_x: u32,
+    tile_min: vec2<i32>,
+    // when set, the copies are white ghosts around the original instead
+    ghosts: u32,
+    // unset when the particles aren't confined to the unit square
+    wrap: u32,
+};
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var<uniform> settings: ParticleSettings;
+// xy: position in the unit square, z: palette index
+@group(#{MATERIAL_BIND_GROUP}) @binding(1) var<storage, read> particles: array<vec4<f32>>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(2) var<storage, read> palette: array<vec4<f32>>;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    // quad * 4 + corner
+    @location(0) index: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let quad = vertex.index / 4u;
+    if quad >= settings.quads {
+        // every corner at the same spot, so there's nothing to rasterize
+        out.clip_position = vec4(0.0, 0.0, 0.0, 1.0);
+        return out;
+    }
+    var corners = array<vec2<f32>, 4>(
+        vec2(-1.0, -1.0),
+        vec2( 1.0, -1.0),
+        vec2( 1.0,  1.0),
+        vec2(-1.0,  1.0),
+    );
+    let offset = corners[vertex.index % 4u] * settings.radius;
+
+    // the original, then the ghosts one tile over to the right, top, left and bottom
+    var ghost_tiles = array<vec2<f32>, 5>(
+        vec2( 0.0,  0.0),
+        vec2( 1.0,  0.0),
+        vec2( 0.0,  1.0),
+        vec2(-1.0,  0.0),
+        vec2( 0.0, -1.0),
+    );
+    let copy = quad % settings.copies;
+    let particle = particles[quad / settings.copies];
+    let ghost = settings.ghosts != 0u;
+
+    var tile: vec2<f32>;
+    if ghost {
+        tile = ghost_tiles[copy];
+    } else {
+        let x = i32(copy % settings.tiles_x);
+        let y = i32(copy / settings.tiles_x);
+        tile = vec2<f32>(settings.tile_min + vec2(x, y));
+    }
+
+    let side = settings.half_side * 2.0;
+    let shifted = particle.xy - settings.center + 0.5;
+    let position = select(shifted, fract(shifted), settings.wrap != 0u);
+    let center = (position * 2.0 - 1.0) * settings.half_side + tile * side;
+    let corner = vec4(center + offset, 0.0, 1.0);
+
+    out.clip_position = mesh2d_position_local_to_clip(get_world_from_local(vertex.instance_index), corner);
+    out.uv = offset / settings.radius;
+    out.color = select(palette[u32(particle.z)], settings.ghost_color, ghost && copy > 0u);
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    if dot(in.uv, in.uv) > 1.0 {
+        discard;
+    }
+    return in.color;
+}
@@ -1,24 +1,218 @@
-// use crate::{
-//     config::ConfigState,
-//     physics::{bodies::PointBody, forces::ForceMatrix}
-// };
-
-// pub struct SaveState {
-//     bodies: Vec<PointBody>,
-//     config: ConfigState,
-//     forces: ForceMatrix,
-// }
-
-// impl SaveState {
-//     pub fn new(bodies: &[&PointBody], config: &ConfigState, forces: &ForceMatrix) -> Self {
-//         Self {
-//             bodies: bodies.iter().map(|c| **c).collect(),
-//             config: config.clone(),
-//             forces: forces.clone(),
-//         }
-//     }
-// }
-
-// fn _ui() {
-
-// }
\ No newline at end of file
+use std::{fs, io, path::Path};
+
+use bevy::prelude::*;
+use bevy_egui::input::EguiWantsInput;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{ConfigState, MAX_BODIES},
+    palette::Palette,
+    physics::{
+        bodies::PointBody,
+        forces::{ForceMatrix, ForceMatrixType},
+        params::PhysicsParams,
+        particles::Particles,
+        physics::ParticlePhysics,
+        species::SpeciesTable,
+    },
+    providers::positioners::PositionerType,
+};
+
+// bump whenever `SaveState` changes in a way older files can't be read
+const SNAPSHOT_VERSION: u32 = 1;
+const DEFAULT_PATH: &str = "snapshot.json";
+
+#[derive(Resource)]
+pub struct SnapshotState {
+    pub path: String,
+    pub status: Option<String>,
+    pub save_requested: bool,
+    pub load_requested: bool,
+}
+
+impl Default for SnapshotState {
+    fn default() -> Self {
+        Self {
+            path: DEFAULT_PATH.to_string(),
+            status: None,
+            save_requested: false,
+            load_requested: false,
+        }
+    }
+}
+
+// the subset of `ConfigState` that describes the simulation rather than the view
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SavedConfig {
+    pub bodies_count: u32,
+    pub colors_count: u8,
+    pub force_matrix_option: ForceMatrixType,
+    pub position_option: PositionerType,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+impl From<&ConfigState> for SavedConfig {
+    fn from(config: &ConfigState) -> Self {
+        Self {
+            bodies_count: config.bodies_count,
+            colors_count: config.colors_count,
+            force_matrix_option: config.force_matrix_option,
+            position_option: config.position_option,
+            seed: config.seed,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SaveState {
+    version: u32,
+    bodies: Vec<PointBody>,
+    config: SavedConfig,
+    forces: ForceMatrix,
+    #[serde(default)]
+    physics: PhysicsParams,
+    #[serde(default)]
+    species: SpeciesTable,
+}
+
+impl SaveState {
+    pub fn new(
+        particles: &Particles,
+        config: &ConfigState,
+        forces: &ForceMatrix,
+        physics: &PhysicsParams,
+        species: &SpeciesTable,
+    ) -> Self {
+        // write out every color's species, not just the ones the ui has touched
+        let mut species = species.clone();
+        species.resize(config.colors_count as usize);
+        Self {
+            version: SNAPSHOT_VERSION,
+            bodies: particles.iter().collect(),
+            config: config.into(),
+            forces: forces.clone(),
+            physics: *physics,
+            species,
+        }
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = io::BufWriter::new(fs::File::create(path)?);
+        serde_json::to_writer(file, self)?;
+        Ok(())
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = io::BufReader::new(fs::File::open(path)?);
+        let mut state: Self = serde_json::from_reader(file)?;
+        state.forces.fill_radii();
+        state.validate()?;
+        Ok(state)
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(invalid(format!(
+                "unsupported snapshot version {} (expected {SNAPSHOT_VERSION})", self.version
+            )));
+        }
+        if self.forces.color_count() != self.config.colors_count as usize {
+            return Err(invalid("force matrix does not match the color count".into()));
+        }
+        if !self.forces.is_valid() {
+            return Err(invalid("force matrix data is malformed".into()));
+        }
+        if !self.species.is_valid() {
+            return Err(invalid("species table is out of range".into()));
+        }
+        if !self.physics.is_valid() {
+            return Err(invalid("physics parameters are out of range".into()));
+        }
+        if self.bodies.len() > MAX_BODIES as usize {
+            return Err(invalid(format!("too many bodies ({})", self.bodies.len())));
+        }
+        if let Some(body) = self.bodies.iter().find(|b| b.color >= self.forces.color_count()) {
+            return Err(invalid(format!("body color {} is out of range", body.color)));
+        }
+        Ok(())
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+pub fn request_save(
+    mut snapshot: ResMut<SnapshotState>,
+    egui_input: Res<EguiWantsInput>,
+) {
+    if egui_input.wants_any_keyboard_input() { return }
+    snapshot.save_requested = true;
+}
+
+pub fn request_load(
+    mut snapshot: ResMut<SnapshotState>,
+    egui_input: Res<EguiWantsInput>,
+) {
+    if egui_input.wants_any_keyboard_input() { return }
+    snapshot.load_requested = true;
+}
+
+pub fn save_snapshot(
+    mut snapshot: ResMut<SnapshotState>,
+    config: Res<ConfigState>,
+    force_matrix: Res<ForceMatrix>,
+    params: Res<PhysicsParams>,
+    physics: Res<ParticlePhysics>,
+    species: Res<SpeciesTable>,
+) {
+    if !snapshot.save_requested { return }
+    snapshot.save_requested = false;
+
+    let particles = physics.particles();
+    let state = SaveState::new(particles, &config, &force_matrix, &params, &species);
+    snapshot.status = Some(match state.write(&snapshot.path) {
+        Ok(()) => format!("saved {} bodies", particles.len()),
+        Err(err) => format!("save failed: {err}"),
+    });
+}
+
+pub fn load_snapshot(
+    mut config: ResMut<ConfigState>,
+    mut force_matrix: ResMut<ForceMatrix>,
+    mut palette: ResMut<Palette>,
+    mut params: ResMut<PhysicsParams>,
+    mut physics: ResMut<ParticlePhysics>,
+    mut snapshot: ResMut<SnapshotState>,
+    mut species: ResMut<SpeciesTable>,
+) {
+    if !snapshot.load_requested { return }
+    snapshot.load_requested = false;
+
+    let state = match SaveState::read(&snapshot.path) {
+        Ok(state) => state,
+        Err(err) => {
+            snapshot.status = Some(format!("load failed: {err}"));
+            return;
+        },
+    };
+
+    config.bodies_count = state.bodies.len() as u32;
+    config.colors_count = state.config.colors_count;
+    config.force_matrix_option = state.config.force_matrix_option;
+    config.position_option = state.config.position_option;
+    config.seed = state.config.seed;
+    config.reset_bodies = false;
+    *force_matrix = state.forces;
+    *params = state.physics;
+    *species = state.species;
+    *palette = Palette::new(config.colors_count as usize);
+
+    // replace the bodies as-is rather than respawning them at random, clearing
+    // first so anything holding body indices sees them change hands
+    let particles = physics.particles_mut();
+    particles.clear();
+    particles.extend(state.bodies.iter().copied());
+    snapshot.status = Some(format!("loaded {} bodies", state.bodies.len()));
+}
@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+use egui_plot::{Legend, Line, Plot};
+use rayon::prelude::*;
+
+use crate::{
+    palette::Palette,
+    physics::{physics::ParticlePhysics, species::SpeciesTable},
+};
+
+// how many frames of history the plots cover
+const HISTORY_LEN: usize = 600;
+const PLOT_HEIGHT: f32 = 90.0;
+
+// what the particles were doing on one frame
+struct Sample {
+    time: f64, // seconds since startup
+    kinetic_energy: f64,
+    mean_speed: f64,
+    max_speed: f64,
+    population: Vec<usize>, // per color
+    species_speed: Vec<f64>, // mean speed per color
+}
+
+// per color running sums, folded over the bodies in parallel
+#[derive(Clone)]
+struct Totals {
+    kinetic_energy: f64,
+    max_speed: f64,
+    speed: Vec<f64>,
+    count: Vec<usize>,
+}
+
+impl Totals {
+    fn new(colors: usize) -> Self {
+        Self { kinetic_energy: 0.0, max_speed: 0.0, speed: vec![0.0; colors], count: vec![0; colors] }
+    }
+
+    fn merge(mut self, other: Self) -> Self {
+        self.kinetic_energy += other.kinetic_energy;
+        self.max_speed = self.max_speed.max(other.max_speed);
+        self.speed.iter_mut().zip(other.speed).for_each(|(a, b)| *a += b);
+        self.count.iter_mut().zip(other.count).for_each(|(a, b)| *a += b);
+        self
+    }
+}
+
+#[derive(Default, Resource)]
+pub struct Statistics {
+    samples: VecDeque<Sample>,
+}
+
+impl Statistics {
+
+    fn push(&mut self, sample: Sample) {
+        if self.samples.len() == HISTORY_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    fn series(&self, value: impl Fn(&Sample) -> Option<f64>) -> Vec<[f64; 2]> {
+        self.samples
+            .iter()
+            .filter_map(|sample| Some([sample.time, value(sample)?]))
+            .collect()
+    }
+
+}
+
+// runs while the simulation does, a paused one would only draw flat lines
+pub fn sample_statistics(
+    mut stats: ResMut<Statistics>,
+    palette: Res<Palette>,
+    physics: Res<ParticlePhysics>,
+    species: Res<SpeciesTable>,
+    time: Res<Time>,
+) {
+    let particles = physics.particles();
+    let colors = palette.len();
+    let masses = (0..colors).map(|color| species.get(color).mass).collect::<Vec<_>>();
+    let totals = particles.velocities
+        .par_iter()
+        .zip(particles.colors.par_iter())
+        .fold(|| Totals::new(colors), |mut totals, (velocity, color)| {
+            let speed = velocity.length();
+            let mass = masses.get(*color).copied().unwrap_or(1.0);
+            totals.kinetic_energy += 0.5 * mass * speed * speed;
+            totals.max_speed = totals.max_speed.max(speed);
+            if let (Some(sum), Some(count)) = (totals.speed.get_mut(*color), totals.count.get_mut(*color)) {
+                *sum += speed;
+                *count += 1;
+            }
+            totals
+        })
+        .reduce(|| Totals::new(colors), Totals::merge);
+
+    let total_speed = totals.speed.iter().sum::<f64>();
+    let counted = totals.count.iter().sum::<usize>();
+    stats.push(Sample {
+        time: time.elapsed_secs_f64(),
+        kinetic_energy: totals.kinetic_energy,
+        mean_speed: match counted {
+            0 => 0.0,
+            n => total_speed / n as f64,
+        },
+        max_speed: totals.max_speed,
+        species_speed: totals.speed
+            .iter()
+            .zip(&totals.count)
+            .map(|(sum, count)| match count {
+                0 => 0.0,
+                n => sum / *n as f64,
+            })
+            .collect(),
+        population: totals.count,
+    });
+}
+
+pub fn stats_ui(
+    mut gui: EguiContexts,
+    palette: Res<Palette>,
+    stats: Res<Statistics>,
+) {
+    let Ok(ctx) = gui.ctx_mut() else { return };
+    egui::Window::new("Statistics")
+        .default_open(false)
+        .default_width(320.0)
+        .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+        .show(ctx, |ui| {
+            if let Some(last) = stats.samples.back() {
+                ui.label(format!(
+                    "energy {:.4}   mean speed {:.4}   max speed {:.4}",
+                    last.kinetic_energy, last.mean_speed, last.max_speed,
+                ));
+            }
+            let plot = |id: &str| Plot::new(id)
+                .height(PLOT_HEIGHT)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .allow_boxed_zoom(false)
+                .show_axes([false, true])
+                .include_y(0.0);
+
+            ui.label("Kinetic energy");
+            plot("kinetic_energy").show(ui, |plot_ui| {
+                plot_ui.line(Line::new("energy", stats.series(|s| Some(s.kinetic_energy))));
+            });
+
+            ui.label("Speed");
+            plot("speed").legend(Legend::default()).show(ui, |plot_ui| {
+                plot_ui.line(Line::new("mean", stats.series(|s| Some(s.mean_speed))));
+                plot_ui.line(Line::new("max", stats.series(|s| Some(s.max_speed))));
+            });
+
+            // one line per color, in the color itself
+            ui.label("Population");
+            plot("population").show(ui, |plot_ui| {
+                for color in 0..palette.len() {
+                    let series = stats.series(|s| s.population.get(color).map(|n| *n as f64));
+                    plot_ui.line(Line::new(format!("color {color}"), series).color(palette.egui_color(color)));
+                }
+            });
+
+            ui.label("Mean speed per color");
+            plot("species_speed").show(ui, |plot_ui| {
+                for color in 0..palette.len() {
+                    let series = stats.series(|s| s.species_speed.get(color).copied());
+                    plot_ui.line(Line::new(format!("color {color}"), series).color(palette.egui_color(color)));
+                }
+            });
+        });
+}
@@ -0,0 +1,48 @@
+use bevy::prelude::Resource;
+use bevy_egui::egui::{self, Color32, Context};
+
+// how long a toast stays up, in seconds
+const TOAST_SECS: f64 = 4.0;
+
+struct Toast {
+    text: String,
+    error: bool,
+    expires: Option<f64>, // set the first frame the toast is shown
+}
+
+// short lived messages in the corner of the screen
+#[derive(Default, Resource)]
+pub struct Toasts(Vec<Toast>);
+
+impl Toasts {
+
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.0.push(Toast { text: text.into(), error: false, expires: None });
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.0.push(Toast { text: text.into(), error: true, expires: None });
+    }
+
+    pub fn toasts_ui(&mut self, ctx: &Context) {
+        let now = ctx.input(|i| i.time);
+        self.0.retain(|toast| toast.expires.is_none_or(|t| t > now));
+        if self.0.is_empty() { return }
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                for toast in &mut self.0 {
+                    toast.expires.get_or_insert(now + TOAST_SECS);
+                    let color = match toast.error {
+                        true => Color32::from_rgb(255, 110, 110),
+                        false => ui.visuals().text_color(),
+                    };
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.colored_label(color, &toast.text);
+                    });
+                }
+            });
+    }
+
+}
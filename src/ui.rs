@@ -1,24 +1,45 @@
 use bevy::{prelude::*, diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin}};
-use bevy_egui::*;
+use bevy_egui::{*, input::EguiWantsInput};
+use glam::DVec2;
 use strum::IntoEnumIterator;
 
 use crate::{AppState, ConfigState, ShowUi};
+use crate::brush::Brush;
+use crate::config::MAX_BODIES;
+use crate::history::MatrixHistory;
 use crate::providers::positioners::PositionerType;
-use crate::physics::forces::ForceMatrix;
+use crate::palette::Palette;
+use crate::presets::PresetLibrary;
+use crate::physics::{forces::ForceMatrix, params::PhysicsParams, physics::ParticlePhysics, species::SpeciesTable};
+use crate::render::{self, TilingMode};
+use crate::rng::SimRng;
+use crate::snapshot::SnapshotState;
+use crate::toasts::Toasts;
 
-const LEFT_PANEL: &'static str = "CONFIG";
+const LEFT_PANEL: &str = "CONFIG";
 
 pub fn ui_system(
+    mut brush: ResMut<Brush>,
     mut config: ResMut<ConfigState>,
     mut force_matrix: ResMut<ForceMatrix>,
     mut gui: EguiContexts,
+    mut history: ResMut<MatrixHistory>,
+    mut params: ResMut<PhysicsParams>,
+    mut presets: ResMut<PresetLibrary>,
+    mut rng: ResMut<SimRng>,
+    mut snapshot: ResMut<SnapshotState>,
+    mut species: ResMut<SpeciesTable>,
+    mut toasts: ResMut<Toasts>,
     mut vis_state: ResMut<NextState<ShowUi>>,
     diagnostics: Res<DiagnosticsStore>,
+    palette: Res<Palette>,
+    physics: Res<ParticlePhysics>,
     key_state: Res<ButtonInput<KeyCode>>,
 ) {
     let Ok(ctx) = gui.ctx_mut() else { return };
 
     egui::SidePanel::left(LEFT_PANEL)
+        .default_width(config.panel_width)
         .show(ctx, |ui| {
             ui.horizontal(|ui| {
                 if ui.button("Hide").clicked() {
@@ -44,21 +65,15 @@ pub fn ui_system(
                         false => 100,
                     };
 
-                    if ui.button(" - ").clicked() && config.bodies_count > 0 {
-                        if inc_amt > config.bodies_count {
-                            config.bodies_count = 0;
-                        } else {
-                            config.bodies_count -= inc_amt;
-                        }
+                    if ui.button(" - ").clicked() {
+                        config.bodies_count = config.bodies_count.saturating_sub(inc_amt);
                     }
-                    if ui.button(" + ").clicked() && config.bodies_count < u16::MAX {
-                        if inc_amt > u16::MAX - config.bodies_count {
-                            config.bodies_count = u16::MAX;
-                        } else {
-                            config.bodies_count += inc_amt;
-                        }
+                    if ui.button(" + ").clicked() {
+                        config.bodies_count = (config.bodies_count + inc_amt).min(MAX_BODIES);
                     }
-                    ui.label(config.bodies_count.to_string());
+                    ui.add(egui::DragValue::new(&mut config.bodies_count)
+                        .speed(100.0)
+                        .range(0..=MAX_BODIES));
                 });
                 // color count
                 ui.label("Color Type Count:");
@@ -69,12 +84,13 @@ pub fn ui_system(
                     }
                     if ui.button(" + ").clicked() && config.colors_count < u8::MAX {
                         config.colors_count += 1;
-                        force_matrix.expand();
+                        force_matrix.expand(&mut *rng);
                     }
                     ui.label(config.colors_count.to_string());
                 });
 
-                force_matrix.force_matrix_ui(ui, &mut config);
+                force_matrix.force_matrix_ui(ui, &mut config, &palette, &mut rng, &mut toasts);
+                ui.collapsing("History", |ui| history.history_ui(ui, &mut force_matrix, &mut config));
 
                 ui.horizontal(|ui| {
                     if ui.button(" Update ").clicked() {
@@ -92,16 +108,105 @@ pub fn ui_system(
                         });
                     ui.end_row();
                 });
+
+                // physics
+                ui.collapsing("Physics", |ui| params.params_ui(ui));
+                ui.collapsing("Species", |ui| species.species_ui(ui, &palette, &params));
+
+                ui.collapsing("Brush", |ui| brush.brush_ui(ui, &palette));
+
+                // view
+                ui.collapsing("View", |ui| {
+                    egui::ComboBox::from_label("Tiling")
+                        .selected_text(format!("{:?}", config.tiling))
+                        .show_ui(ui, |ui| {
+                            for t in TilingMode::iter() {
+                                ui.selectable_value(&mut config.tiling, t, format!("{t}"));
+                            }
+                        });
+                    let particles = physics.particles();
+                    ui.horizontal(|ui| {
+                        ui.label("Center on");
+                        let last = particles.len().saturating_sub(1);
+                        ui.add(egui::DragValue::new(&mut config.focus_ix).range(0..=last));
+                    });
+                    ui.horizontal(|ui| {
+                        let has_focus = config.focus_ix < particles.len();
+                        if ui.add_enabled(has_focus, egui::Button::new(" Particle ")).clicked() {
+                            config.view_center = particles.positions[config.focus_ix];
+                        }
+                        if ui.add_enabled(has_focus, egui::Button::new(" Cluster "))
+                            .on_hover_text("Center on the particles around it")
+                            .clicked()
+                        {
+                            // a few interaction radii takes in the particle's clump
+                            let radius = 4.0 * params.max_dist;
+                            config.view_center = render::cluster_center(particles, config.focus_ix, radius, params.boundary);
+                        }
+                        if ui.button(" Reset ").clicked() {
+                            config.view_center = DVec2::splat(0.5);
+                        }
+                    });
+                });
+
+                // seed
+                ui.label("Seed:");
+                ui.horizontal(|ui| {
+                    // seeds span all of u64, more than a DragValue's f64 holds exactly;
+                    // the text lives in egui's memory so it can be cleared and retyped
+                    let id = ui.make_persistent_id("seed");
+                    let mut text = ui.data_mut(|data| data.get_temp::<String>(id))
+                        .unwrap_or_else(|| config.seed.to_string());
+                    let response = ui.add(egui::TextEdit::singleline(&mut text).desired_width(150.0));
+                    if response.changed()
+                        && let Ok(seed) = text.parse()
+                    {
+                        config.seed = seed;
+                    }
+                    // out of focus it shows the seed in use, re-rolls and loads included
+                    if !response.has_focus() {
+                        text = config.seed.to_string();
+                    }
+                    ui.data_mut(|data| data.insert_temp(id, text));
+                    if ui.button(" 🎲 ").on_hover_text("Pick a new seed").clicked() {
+                        config.seed = rand::random();
+                    }
+                    if ui.button(" Restart ").on_hover_text("Rebuild forces and particles from the seed").clicked() {
+                        config.restart = true;
+                    }
+                });
+
+                ui.collapsing("Presets", |ui| presets.presets_ui(ui, &mut toasts));
+
+                // snapshots
+                ui.separator();
+                ui.label("Snapshot:");
+                ui.text_edit_singleline(&mut snapshot.path);
+                ui.horizontal(|ui| {
+                    if ui.button(" Save ").on_hover_text("F5").clicked() {
+                        snapshot.save_requested = true;
+                    }
+                    if ui.button(" Load ").on_hover_text("F9").clicked() {
+                        snapshot.load_requested = true;
+                    }
+                });
+                if let Some(status) = &snapshot.status {
+                    ui.label(status);
+                }
             });
 
         });
 
+    toasts.toasts_ui(ctx);
 }
 
 pub fn toggle_running(
     mut next_state: ResMut<NextState<AppState>>,
     state: Res<State<AppState>>,
+    egui_input: Res<EguiWantsInput>,
 ) {
+    // the key is being typed into a text field
+    if egui_input.wants_any_keyboard_input() { return }
     match state.get() {
         AppState::Running => next_state.set(AppState::Paused),
         AppState::Paused => next_state.set(AppState::Running),
@@ -111,7 +216,9 @@ pub fn toggle_running(
 pub fn toggle_visible(
     mut next_state: ResMut<NextState<ShowUi>>,
     state: Res<State<ShowUi>>,
+    egui_input: Res<EguiWantsInput>,
 ) {
+    if egui_input.wants_any_keyboard_input() { return }
     match state.get() {
         ShowUi::Yes => next_state.set(ShowUi::No),
         ShowUi::No => next_state.set(ShowUi::Yes),
@@ -120,6 +227,8 @@ pub fn toggle_visible(
 
 pub fn negate_forces(
     mut force_matrix: ResMut<ForceMatrix>,
+    egui_input: Res<EguiWantsInput>,
 ) {
+    if egui_input.wants_any_keyboard_input() { return }
     force_matrix.negate();
 }
\ No newline at end of file